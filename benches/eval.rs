@@ -0,0 +1,43 @@
+// Times evaluation on its own, without lexing and parsing. Run with
+// `cargo bench`
+use std::time::Duration;
+
+use term::interpreter::{self, PassManager, State};
+
+// Runs the source into a fresh state `runs` times and returns the fastest
+// evaluation, which is the least disturbed by everything else on the machine
+fn time(src: &str, runs: usize) -> Duration {
+    (0..runs).map(|_| {
+        let mut state = State::new();
+        // Folding would leave nothing to evaluate in the constant cases
+        state.passes = PassManager::for_level(0);
+        let (_, timings) = interpreter::interpret_timed(src, &mut state).expect("the benchmark should run");
+        timings.eval
+    }).min().unwrap_or_default()
+}
+
+// `lines` statements, each a left-nested chain of `terms` repetitions of
+// `term` after `first`
+fn chains(setup: &str, first: &str, term: &str, terms: usize, lines: usize) -> String {
+    let mut src = String::from(setup);
+
+    for _ in 0..lines {
+        src += "c = ";
+        src += first;
+        src += &term.repeat(terms);
+        src += "\n";
+    }
+
+    src
+}
+
+fn report(name: &str, src: &str) {
+    let best = time(src, 20);
+    let operators = src.matches(['+', '-', '*']).count();
+    println!("{:<12} {:>10.2?} {:>8.1} ns/operator", name, best, best.as_nanos() as f64 / operators as f64);
+}
+
+fn main() {
+    report("variables", &chains("a = 1\nb = 2.5\n", "a", " + a * b - a", 200, 200));
+    report("constants", &chains("", "1", " + 2 * 3 - 4", 200, 200));
+}
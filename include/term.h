@@ -0,0 +1,34 @@
+/* C interface to the term interpreter, built with
+ * `cargo rustc --lib --release --features ffi --crate-type cdylib`, or
+ * staticlib for a static library.
+ *
+ * Strings are NUL-terminated UTF-8. Every string returned by these functions
+ * belongs to the caller and is freed with term_string_free. */
+#ifndef TERM_H
+#define TERM_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TermInterpreter TermInterpreter;
+
+TermInterpreter *term_new(void);
+
+/* Runs the source. Returns 0 and stores the value of the last statement in
+ * `out`, or returns -1 and stores the error message in `out`. `out` may be
+ * NULL. */
+int term_eval(TermInterpreter *interpreter, const char *src, char **out);
+
+/* The value of a variable as text, or NULL if there is no such variable. */
+char *term_get_var(const TermInterpreter *interpreter, const char *name);
+
+void term_free(TermInterpreter *interpreter);
+
+void term_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,49 @@
+use std::env;
+use std::io::IsTerminal;
+
+use crate::interpreter::ValueKind;
+
+#[derive(Clone, Copy)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Magenta,
+    Dim
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Green => "32",
+            Color::Yellow => "33",
+            Color::Magenta => "35",
+            Color::Dim => "2"
+        }
+    }
+
+    // The color REPL output uses for values of this kind
+    pub fn of(value: &ValueKind) -> Color {
+        match value {
+            ValueKind::Integer(_) | ValueKind::Decimal(_) | ValueKind::BigInt(_) | ValueKind::Fixed(_) | ValueKind::Rational(..) => Color::Yellow,
+            ValueKind::Str(_) | ValueKind::Bytes(_) => Color::Green,
+            ValueKind::Boolean(_) => Color::Magenta,
+            _ => Color::Dim
+        }
+    }
+}
+
+// Colors are only used on a terminal, and never when NO_COLOR is set
+// (see https://no-color.org)
+pub fn supported() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && std::io::stdout().is_terminal()
+}
+
+pub fn paint(text: &str, color: Color, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", color.code(), text)
+    } else {
+        text.to_string()
+    }
+}
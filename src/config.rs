@@ -0,0 +1,94 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use term::interpreter::DEFAULT_STACK_SIZE;
+
+// Settings read from ~/.termrc on startup. Every line has the form
+// `key = value`, and lines starting with # are comments
+pub struct Config {
+    pub prompt: String,
+    pub stack_size: usize,
+    pub color: bool,
+    // Like passing --sandbox every time
+    pub sandbox: bool,
+    // Script run into the REPL's state before the first prompt
+    pub init: Option<PathBuf>
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            prompt: String::from(">>> "),
+            stack_size: DEFAULT_STACK_SIZE,
+            color: true,
+            sandbox: false,
+            init: None
+        }
+    }
+
+    // Falls back to the defaults when there is no config file
+    pub fn load() -> Config {
+        match home_dir().map(|home| fs::read_to_string(home.join(".termrc"))) {
+            Some(Ok(src)) => Config::parse(&src),
+            _ => Config::new()
+        }
+    }
+
+    fn parse(src: &str) -> Config {
+        let mut config = Config::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once('=') {
+                Some((key, value)) => config.set(key.trim(), value.trim(), i + 1),
+                None => eprintln!("~/.termrc:{}: expected `key = value`", i + 1)
+            }
+        }
+
+        config
+    }
+
+    fn set(&mut self, key: &str, value: &str, line: usize) {
+        match key {
+            "prompt" => self.prompt = unquote(value).to_string(),
+            "stack_size" => match value.parse() {
+                Ok(n) if n > 0 => self.stack_size = n,
+                _ => eprintln!("~/.termrc:{}: stack_size should be a positive integer", line)
+            },
+            "color" => match value {
+                "on" | "true" => self.color = true,
+                "off" | "false" => self.color = false,
+                _ => eprintln!("~/.termrc:{}: color should be on or off", line)
+            },
+            "sandbox" => match value {
+                "on" | "true" => self.sandbox = true,
+                "off" | "false" => self.sandbox = false,
+                _ => eprintln!("~/.termrc:{}: sandbox should be on or off", line)
+            },
+            "init" => self.init = Some(expand_home(unquote(value))),
+            other => eprintln!("~/.termrc:{}: unknown option '{}'", line, other)
+        }
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+// Quotes are optional, but needed to keep leading or trailing spaces
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path)
+    }
+}
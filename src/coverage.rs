@@ -0,0 +1,77 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+use term::interpreter::{parser, EvalObserver, Node};
+
+// How many times the statement starting on each line of a file ran
+#[derive(Default)]
+pub struct Coverage {
+    // The line of the file the source being run starts at, as spans count
+    // from the start of that source
+    pub first_line: usize,
+    hits: HashMap<usize, u64>
+}
+
+// The lines of `src` a statement starts on, whether it ran or not
+fn statement_lines(src: &str) -> BTreeSet<usize> {
+    match parser::lex(src) {
+        Ok(tokens) => parser::parse_program(&tokens).0.iter().map(|s| s.span.line).collect(),
+        Err(_) => BTreeSet::new()
+    }
+}
+
+impl Coverage {
+    // How many statements ran out of all of them, and the lines of those
+    // that never did
+    pub fn report(&self, name: &str, src: &str) -> String {
+        let lines = statement_lines(src);
+        let missed: Vec<String> = lines.iter()
+            .filter(|line| !self.hits.contains_key(line))
+            .map(usize::to_string)
+            .collect();
+
+        let covered = lines.len() - missed.len();
+        let percent = if lines.is_empty() { 100.0 } else { covered as f64 * 100.0 / lines.len() as f64 };
+        let mut out = format!("[coverage] {}: {} of {} statements ran ({:.1}%)\n", name, covered, lines.len(), percent);
+
+        if !missed.is_empty() {
+            out += &format!("[coverage] never ran: lines {}\n", missed.join(", "));
+        }
+
+        out
+    }
+
+    // The same in the lcov tracefile format, which coverage tools and CI
+    // services read
+    pub fn lcov(&self, path: &str, src: &str) -> String {
+        let lines = statement_lines(src);
+        let mut out = format!("TN:\nSF:{}\n", path);
+
+        for line in &lines {
+            out += &format!("DA:{},{}\n", line, self.hits.get(line).unwrap_or(&0));
+        }
+
+        let hit = lines.iter().filter(|line| self.hits.contains_key(line)).count();
+        out += &format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), hit);
+        out
+    }
+}
+
+// Counts statements as they start
+pub struct CoverageObserver {
+    coverage: Arc<Mutex<Coverage>>
+}
+
+impl CoverageObserver {
+    pub fn new(coverage: Arc<Mutex<Coverage>>) -> CoverageObserver {
+        CoverageObserver { coverage }
+    }
+}
+
+impl EvalObserver for CoverageObserver {
+    fn statement_start(&mut self, statement: &Node) {
+        let mut coverage = self.coverage.lock().unwrap();
+        let line = coverage.first_line + statement.span.line - 1;
+        *coverage.hits.entry(line).or_default() += 1;
+    }
+}
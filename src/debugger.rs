@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use term::diagnostics::Diagnostic;
+use term::interpreter::{self, Hook, Node, State};
+
+use crate::{statement_lines, Options};
+
+#[derive(PartialEq)]
+enum Mode {
+    // Stop before every node that gets evaluated
+    Step,
+    // Stop before the next line
+    Next,
+    // Run until a breakpoint is hit
+    Continue,
+    Quit
+}
+
+struct Controller {
+    breakpoints: HashSet<usize>,
+    mode: Mode,
+    line: usize
+}
+
+impl Controller {
+    fn new() -> Controller {
+        Controller {
+            breakpoints: HashSet::new(),
+            mode: Mode::Next,
+            line: 0
+        }
+    }
+
+    // Reads debugger commands until one of them resumes execution
+    fn prompt(&mut self, source: &str, state: &mut State) {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+
+        loop {
+            print!("(dbg) ");
+            stdout.flush().unwrap();
+
+            let mut input = String::new();
+            if stdin.read_line(&mut input).unwrap_or(0) == 0 {
+                self.mode = Mode::Quit;
+                return;
+            }
+
+            let mut parts = input.trim().splitn(2, ' ');
+            let cmd = parts.next().unwrap_or("");
+            let arg = parts.next().unwrap_or("").trim();
+
+            match cmd {
+                "s" | "step" => { self.mode = Mode::Step; return; },
+                "n" | "next" => { self.mode = Mode::Next; return; },
+                "c" | "continue" => { self.mode = Mode::Continue; return; },
+                "q" | "quit" => { self.mode = Mode::Quit; return; },
+                "b" | "break" => match arg.parse::<usize>() {
+                    Ok(n) => {
+                        self.breakpoints.insert(n);
+                        println!("Breakpoint set at line {}", n);
+                    },
+                    Err(_) => println!("Usage: break <line>")
+                },
+                "d" | "delete" => match arg.parse::<usize>() {
+                    Ok(n) if self.breakpoints.remove(&n) => println!("Breakpoint at line {} removed", n),
+                    _ => println!("No breakpoint at line {}", arg)
+                },
+                "p" | "print" => match state.get_var(arg) {
+                    Some(v) => println!("{} = {}", arg, v),
+                    None => println!("No such variable: {}", arg)
+                },
+                "set" => {
+                    if arg.contains('=') {
+                        if let Err(e) = interpreter::interpret(arg, state) {
+                            println!("error: {}", e);
+                        }
+                    } else {
+                        println!("Usage: set <name> = <expr>");
+                    }
+                },
+                "vars" => {
+                    for (name, value) in state.vars() {
+                        println!("{} = {}", name, value);
+                    }
+                },
+                "stack" => {
+                    for (i, value) in state.stack_contents().iter().rev().enumerate() {
+                        println!("{:>3}: {}", i, value);
+                    }
+                },
+                "l" | "list" => println!("{:>4} | {}", self.line, source),
+                "" => {},
+                "h" | "help" => println!(
+                    "Commands: step, next, continue, break <line>, delete <line>, print <name>, set <name> = <expr>, vars, stack, list, quit"
+                ),
+                other => println!("Unknown command: {}", other)
+            }
+        }
+    }
+}
+
+// Installed into the state so the debugger can stop in the middle of a line
+struct DebugHook {
+    controller: Arc<Mutex<Controller>>,
+    source: String
+}
+
+impl Hook for DebugHook {
+    fn enter_node(&mut self, node: &Node, state: &mut State) {
+        let mut controller = self.controller.lock().unwrap();
+
+        if controller.mode == Mode::Step {
+            println!("evaluating {}", node.entry);
+            controller.prompt(&self.source, state);
+        }
+    }
+}
+
+// Runs the file a statement at a time, with the state the options would
+// give any other run, so limits, strict mode and the sandbox still apply
+pub fn debug(path: &str, options: &Options) {
+    let source = fs::read_to_string(path).expect("File not found!");
+    let controller = Arc::new(Mutex::new(Controller::new()));
+    let mut state = options.new_state();
+
+    for (line_number, chunk) in statement_lines(&source) {
+        let lines = line_number..line_number + chunk.lines().count();
+
+        {
+            let mut controller = controller.lock().unwrap();
+            controller.line = line_number;
+
+            if controller.mode != Mode::Continue || lines.clone().any(|line| controller.breakpoints.contains(&line)) {
+                for (line, text) in lines.zip(chunk.lines()) {
+                    println!("{:>4} | {}", line, text);
+                }
+                controller.prompt(&chunk, &mut state);
+            }
+
+            if controller.mode == Mode::Quit {
+                return;
+            }
+        }
+
+        state.hook = Some(Box::new(DebugHook {
+            controller: Arc::clone(&controller),
+            source: chunk.clone()
+        }));
+        let result = interpreter::interpret(&chunk, &mut state);
+        state.hook = None;
+
+        if let Err(e) = result {
+            for diagnostic in Diagnostic::from_error(&e) {
+                eprint!("{}", diagnostic.render(path, &chunk, line_number, false));
+            }
+            return;
+        }
+
+        if controller.lock().unwrap().mode == Mode::Quit {
+            return;
+        }
+    }
+
+    println!("Program finished");
+}
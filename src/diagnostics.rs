@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use crate::color::{self, Color};
+use crate::interpreter::{Error, Span, Warning};
+use crate::json::Json;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        }
+    }
+}
+
+// How diagnostics are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ErrorFormat {
+    // Source snippets with an underline, for people
+    #[default]
+    Human,
+    // One JSON object per line, for editors and scripts
+    Json
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ErrorFormat, String> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            other => Err(format!("Unknown error format '{}', expected human or json", other))
+        }
+    }
+}
+
+// A message tied to a location in the source
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span
+}
+
+impl Diagnostic {
+    // One diagnostic per problem, since a syntax error can hold several
+    pub fn from_error(e: &Error) -> Vec<Diagnostic> {
+        match e {
+            Error::Lex(lex) => vec![Diagnostic::error(lex.kind.code(), lex.to_string(), lex.span)],
+            Error::Syntax(errors) => errors.iter()
+                .map(|p| Diagnostic::error("syntax", p.to_string(), p.span))
+                .collect(),
+            Error::Runtime(runtime) => vec![Diagnostic::error(runtime.kind.code(), runtime.to_string(), runtime.span)],
+            Error::Io(message) => vec![Diagnostic::error("io", message.clone(), Span::default())]
+        }
+    }
+
+    pub fn from_warning(warning: &Warning) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: warning.code,
+            message: warning.to_string(),
+            span: warning.span
+        }
+    }
+
+    fn error(code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic { severity: Severity::Error, code, message, span }
+    }
+
+    // The diagnostic in the given format, ending with a newline
+    pub fn format(&self, format: ErrorFormat, name: &str, src: &str, first_line: usize, colored: bool) -> String {
+        match format {
+            ErrorFormat::Human => self.render(name, src, first_line, colored),
+            ErrorFormat::Json => self.to_json(name, first_line).to_string() + "\n"
+        }
+    }
+
+    // A record like
+    //
+    //     {"severity":"error","code":"syntax","message":"...","file":"main.term",
+    //      "span":{"line":3,"col":7,"len":1}}
+    pub fn to_json(&self, name: &str, first_line: usize) -> Json {
+        Json::object(vec![
+            ("severity", self.severity.name().into()),
+            ("code", self.code.into()),
+            ("message", self.message.as_str().into()),
+            ("file", name.into()),
+            ("span", Json::object(vec![
+                ("line", (first_line + self.span.line - 1).into()),
+                ("col", self.span.col.into()),
+                ("len", self.span.len.into())
+            ]))
+        ])
+    }
+
+    // Renders the diagnostic like
+    //
+    //     error: Expected ) but found EOF
+    //      --> main.term:3:7
+    //       |
+    //     3 | a = (1
+    //       |       ^
+    //
+    // where `src` is the source the span points into and `first_line` is the
+    // line number `src` starts at in `name`. A diagnostic without a location
+    // is just the first line
+    pub fn render(&self, name: &str, src: &str, first_line: usize, colored: bool) -> String {
+        let (label, label_color) = match self.severity {
+            Severity::Error => ("error:", Color::Red),
+            Severity::Warning => ("warning:", Color::Yellow)
+        };
+
+        let mut out = format!("{} {}\n", color::paint(label, label_color, colored), self.message);
+
+        if self.span == Span::default() {
+            return out;
+        }
+
+        let line_number = (first_line + self.span.line - 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+        let source_line = src.lines().nth(self.span.line - 1).unwrap_or("");
+        let underline = "^".repeat(self.span.len.max(1));
+
+        out += &format!("{}--> {}:{}:{}\n", gutter, name, line_number, self.span.col);
+        out += &format!("{} |\n", gutter);
+        out += &format!("{} | {}\n", line_number, source_line);
+        out += &format!(
+            "{} | {}{}\n",
+            gutter,
+            " ".repeat(self.span.col - 1),
+            color::paint(&underline, label_color, colored)
+        );
+
+        out
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::interpreter::{self, CancelToken, Capabilities, Completion, EvalObserver, Error, Limits, RuntimeError, Snapshot, State, ValueKind, DEFAULT_STACK_SIZE, PRELUDE};
+
+// Values as seen by programs embedding the interpreter
+pub type Value = ValueKind;
+
+// An interpreter for host applications. Variables persist between calls, so
+// a program can be fed to it piece by piece.
+//
+// Every interpreter owns all of its state, so separate interpreters can run
+// on separate threads at the same time without any locking, and one can be
+// moved to another thread between calls. Host functions, hooks and passes
+// must be Send + Sync for that reason
+pub struct Interpreter {
+    state: State
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_state(State::new())
+    }
+
+    // For a different prelude or globals bound before it runs
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    // Useful to configure the state first, e.g. its overflow policy
+    pub fn with_state(state: State) -> Interpreter {
+        Interpreter { state }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    // Runs the source and returns the value of its last statement
+    pub fn eval(&mut self, src: &str) -> Result<Value, Error> {
+        interpreter::interpret(src, &mut self.state)
+    }
+
+    pub fn eval_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, Error> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path)
+            .map_err(|e| Error::Io(format!("Can't read {}: {}", path.display(), e)))?;
+
+        self.eval(&src)
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<&Value> {
+        self.state.get_var(name)
+    }
+
+    pub fn set_var<V: Into<Value>>(&mut self, name: &str, value: V) {
+        self.state.set_var(name, value.into());
+    }
+
+    // Bounds every later call to `eval` to `max_ops` evaluated nodes and
+    // `max_duration` of wall-clock time. Going over either one makes `eval`
+    // fail with a limit-exceeded runtime error
+    pub fn set_limits(&mut self, max_ops: u64, max_duration: Duration) {
+        self.state.limits = Limits { max_ops: Some(max_ops), max_duration: Some(max_duration) };
+    }
+
+    // A handle that stops the current `eval` from another thread. After
+    // cancelling, reset it before evaluating again
+    pub fn cancel_token(&self) -> CancelToken {
+        self.state.cancel.clone()
+    }
+
+    // What could be typed at byte offset `cursor` of `line`: the variables,
+    // builtins and keywords starting with the word before it
+    pub fn complete(&self, line: &str, cursor: usize) -> Vec<Completion> {
+        interpreter::complete::complete(&self.state, line, cursor)
+    }
+
+    // Checkpoints for speculative evaluation: take a snapshot, eval, and
+    // restore it to roll the variables back
+    pub fn snapshot(&self) -> Snapshot {
+        self.state.snapshot()
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.state.restore(snapshot);
+    }
+
+    // Gets `observer` told about statements, variables, calls and errors as
+    // scripts run
+    pub fn add_observer<O: EvalObserver + 'static>(&mut self, observer: O) {
+        self.state.add_observer(Box::new(observer));
+    }
+
+    // Calls `f` with the name, old and new value of every variable a script
+    // assigns
+    pub fn on_change<F>(&mut self, f: F) where F: Fn(&str, Option<&Value>, &Value) + Send + Sync + 'static {
+        self.state.on_change(f);
+    }
+
+    // Registers a builtin that scripts can call like `name(a, b)`
+    pub fn register_fn<F>(&mut self, name: &str, f: F) where F: Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static {
+        self.state.register_fn(name, f);
+    }
+
+    // Registers a builtin with side effects, which only runs when the
+    // interpreter grants everything in `requires`
+    pub fn register_fn_requiring<F>(&mut self, name: &str, requires: Capabilities, f: F)
+    where F: Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static {
+        self.state.register_fn_requiring(name, requires, f);
+    }
+
+    // Limits what builtins may do, e.g. Capabilities::NONE for untrusted code
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.state.capabilities = capabilities;
+    }
+
+    // Calls a function directly from the host. The error has no location,
+    // since there is no source to point into
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        self.state.call_function(name, args).map_err(|kind| Error::Runtime(RuntimeError { kind, span: Default::default() }))
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+// Sets up what an interpreter starts with before any user code runs. The
+// globals are bound first, so the prelude can build on them, and host
+// functions can be registered on the built interpreter
+pub struct InterpreterBuilder {
+    prelude: String,
+    globals: Vec<(String, Value)>
+}
+
+impl InterpreterBuilder {
+    // Replaces the standard prelude, so an empty source means none at all
+    pub fn with_prelude(mut self, source: &str) -> InterpreterBuilder {
+        self.prelude = source.to_string();
+        self
+    }
+
+    // Runs `source` after the prelude, keeping the standard one
+    pub fn extend_prelude(mut self, source: &str) -> InterpreterBuilder {
+        self.prelude.push('\n');
+        self.prelude.push_str(source);
+        self
+    }
+
+    pub fn with_globals<I, K, V>(mut self, globals: I) -> InterpreterBuilder
+    where I: IntoIterator<Item = (K, V)>, K: Into<String>, V: Into<Value> {
+        self.globals.extend(globals.into_iter().map(|(name, value)| (name.into(), value.into())));
+        self
+    }
+
+    // Fails with the error of the prelude if it doesn't run
+    pub fn build(self) -> Result<Interpreter, Error> {
+        let mut state = State::with_stack_size(DEFAULT_STACK_SIZE);
+        state.set_constants();
+
+        for (name, value) in self.globals {
+            state.set_var(&name, value);
+        }
+
+        state.run_prelude(&self.prelude)?;
+        Ok(Interpreter::with_state(state))
+    }
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> InterpreterBuilder {
+        InterpreterBuilder { prelude: PRELUDE.to_string(), globals: Vec::new() }
+    }
+}
+
+// Fails to compile if anything in the interpreter stops being thread safe
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Interpreter>();
+    assert_send_sync::<State>();
+    assert_send_sync::<Value>();
+    assert_send_sync::<Error>();
+};
@@ -0,0 +1,101 @@
+// A C interface to the interpreter, built with the `ffi` feature. The
+// declarations for C and C++ are in include/term.h.
+//
+// Strings cross the boundary as NUL-terminated UTF-8. Every string returned
+// here is owned by the caller and freed with term_string_free. The safety
+// requirements of each function are in the comment above it
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::engine::{Interpreter, Value};
+
+// The text C gets for a value
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::Integer(n) => n.to_string(),
+        Value::Decimal(n) => n.to_string(),
+        Value::BigInt(n) => n.to_string(),
+        Value::Fixed(n) => n.to_string(),
+        Value::Rational(n, d) => format!("{}/{}", n, d),
+        Value::Str(s) => s.to_string(),
+        Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Identifier(_) | Value::None => String::new()
+    }
+}
+
+// NUL bytes can't be inside a C string, so the text stops at the first one
+fn to_c_string(text: String) -> *mut c_char {
+    let text = match text.find('\0') {
+        Some(end) => &text[..end],
+        None => &text
+    };
+
+    CString::new(text).unwrap_or_default().into_raw()
+}
+
+// None for a null pointer or text that isn't UTF-8
+unsafe fn to_str<'a>(text: *const c_char) -> Option<&'a str> {
+    if text.is_null() {
+        None
+    } else {
+        CStr::from_ptr(text).to_str().ok()
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn term_new() -> *mut Interpreter {
+    Box::into_raw(Box::new(Interpreter::new()))
+}
+
+// Runs the source and returns 0, with the value of the last statement in
+// `out`, or returns -1 with the error message in `out`. `out` may be null
+// when the caller doesn't need it. `interpreter` must come from term_new,
+// and `src` must be a valid C string
+#[no_mangle]
+pub unsafe extern "C" fn term_eval(interpreter: *mut Interpreter, src: *const c_char, out: *mut *mut c_char) -> c_int {
+    let (status, text) = match (interpreter.as_mut(), to_str(src)) {
+        (Some(interpreter), Some(src)) => match interpreter.eval(src) {
+            Ok(value) => (0, to_text(&value)),
+            Err(e) => (-1, e.to_string())
+        },
+        (None, _) => (-1, String::from("The interpreter is null")),
+        (_, None) => (-1, String::from("The source is null or not UTF-8"))
+    };
+
+    if !out.is_null() {
+        *out = to_c_string(text);
+    }
+
+    status
+}
+
+// The value of the variable as text, or null if there is no such variable.
+// `interpreter` must come from term_new, and `name` must be a valid C string
+#[no_mangle]
+pub unsafe extern "C" fn term_get_var(interpreter: *const Interpreter, name: *const c_char) -> *mut c_char {
+    match (interpreter.as_ref(), to_str(name)) {
+        (Some(interpreter), Some(name)) => interpreter.get_var(name).map_or(ptr::null_mut(), |value| to_c_string(to_text(value))),
+        _ => ptr::null_mut()
+    }
+}
+
+// `interpreter` must come from term_new and not be used afterwards. Null is
+// ignored
+#[no_mangle]
+pub unsafe extern "C" fn term_free(interpreter: *mut Interpreter) {
+    if !interpreter.is_null() {
+        drop(Box::from_raw(interpreter));
+    }
+}
+
+// `text` must be a string returned by this library and not be used
+// afterwards. Null is ignored
+#[no_mangle]
+pub unsafe extern "C" fn term_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
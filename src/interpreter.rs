@@ -1,19 +1,231 @@
-mod parser;
+pub mod parser;
+mod analysis;
+mod resolve;
+pub mod optimize;
+pub mod lint;
+pub mod types;
+pub mod symbol;
+pub mod capabilities;
+pub mod complete;
+pub mod bigint;
+pub mod fixed;
+mod compare;
+mod tcp;
+mod bytes;
+mod strings;
+mod format;
+mod machine;
 
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
 use parser::lex::lex::TokenKind;
-use parser::Node;
+pub use parser::{LexError, Node, ParseError, Span};
+pub use analysis::{has_effect, Warning};
+pub use optimize::{AstPass, PassManager};
+pub use symbol::{Interner, Symbol};
+pub use capabilities::Capabilities;
+pub use complete::{Completion, CompletionKind};
+pub use bigint::BigInt;
+pub use fixed::Fixed;
 
 #[derive(Debug, Clone)]
 pub enum ValueKind {
     Integer(i64),
     Decimal(f64),
-    Str(String),
-    Identifier(String),
+    // Shared like strings, as the digits can get long
+    BigInt(Arc<BigInt>),
+    // A decimal without the rounding errors of f64, for money and the like.
+    // Shared too, as it is twice the size of the other numbers
+    Fixed(Arc<Fixed>),
+    // A fraction in lowest terms, with a denominator above 1, as from 1 / 3
+    Rational(i64, i64),
+    // Shared, so copying a string value doesn't copy its text. Arc rather
+    // than Rc keeps values Send
+    Str(Arc<str>),
+    // Binary data, shared the same way
+    Bytes(Arc<[u8]>),
+    Identifier(Symbol),
     Boolean(bool),
     None
 }
 
+impl ValueKind {
+    // The name of the value's runtime type, as shown to users
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueKind::Integer(_) => "integer",
+            ValueKind::Decimal(_) => "decimal",
+            ValueKind::BigInt(_) => "big integer",
+            ValueKind::Fixed(_) => "exact decimal",
+            ValueKind::Rational(..) => "rational",
+            ValueKind::Str(_) => "string",
+            ValueKind::Bytes(_) => "bytes",
+            ValueKind::Identifier(_) => "identifier",
+            ValueKind::Boolean(_) => "boolean",
+            ValueKind::None => "none"
+        }
+    }
+}
+
+// Writes the value the way it is written in a script, so 5, 2.0, 10n, 1.10d,
+// "text" and true, with 1/3 for a rational and none for no value. There
+// are no escapes in strings, so the quotes are just put around the text,
+// while bytes are escaped like b"\x00" unless they are printable ASCII
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueKind::Integer(n) => write!(f, "{}", n),
+            // Debug keeps the point on whole numbers, telling them apart
+            // from integers
+            ValueKind::Decimal(n) => write!(f, "{:?}", n),
+            ValueKind::BigInt(n) => write!(f, "{}n", n),
+            ValueKind::Fixed(n) => write!(f, "{}d", n),
+            ValueKind::Rational(n, d) => write!(f, "{}/{}", n, d),
+            ValueKind::Str(s) => write!(f, "\"{}\"", s),
+            ValueKind::Bytes(bytes) => write!(f, "{}", parser::lex::lex::escape_bytes(bytes)),
+            ValueKind::Identifier(symbol) => write!(f, "<identifier {}>", symbol.index()),
+            ValueKind::Boolean(b) => write!(f, "{}", b),
+            ValueKind::None => write!(f, "none")
+        }
+    }
+}
+
+impl From<i64> for ValueKind {
+    fn from(other: i64) -> ValueKind {
+        ValueKind::Integer(other)
+    }
+}
+
+impl From<f64> for ValueKind {
+    fn from(other: f64) -> ValueKind {
+        ValueKind::Decimal(other)
+    }
+}
+
+impl From<BigInt> for ValueKind {
+    fn from(other: BigInt) -> ValueKind {
+        ValueKind::BigInt(Arc::new(other))
+    }
+}
+
+impl From<Fixed> for ValueKind {
+    fn from(other: Fixed) -> ValueKind {
+        ValueKind::Fixed(Arc::new(other))
+    }
+}
+
+impl From<bool> for ValueKind {
+    fn from(other: bool) -> ValueKind {
+        ValueKind::Boolean(other)
+    }
+}
+
+impl From<String> for ValueKind {
+    fn from(other: String) -> ValueKind {
+        ValueKind::Str(Arc::from(other))
+    }
+}
+
+impl From<&str> for ValueKind {
+    fn from(other: &str) -> ValueKind {
+        ValueKind::Str(Arc::from(other))
+    }
+}
+
+impl From<Vec<u8>> for ValueKind {
+    fn from(other: Vec<u8>) -> ValueKind {
+        ValueKind::Bytes(Arc::from(other))
+    }
+}
+
+// A value couldn't be turned into the requested Rust type
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub found: &'static str
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected {}, but found {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl ValueKind {
+    fn conversion_error(&self, expected: &'static str) -> ConversionError {
+        ConversionError { expected, found: self.type_name() }
+    }
+}
+
+impl TryFrom<ValueKind> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(other: ValueKind) -> Result<i64, ConversionError> {
+        match other {
+            ValueKind::Integer(n) => Ok(n),
+            ValueKind::BigInt(ref n) => n.to_i64().ok_or_else(|| other.conversion_error("integer")),
+            other => Err(other.conversion_error("integer"))
+        }
+    }
+}
+
+// Integers are accepted too, like in arithmetic
+impl TryFrom<ValueKind> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(other: ValueKind) -> Result<f64, ConversionError> {
+        match other {
+            ValueKind::Decimal(n) => Ok(n),
+            ValueKind::Integer(n) => Ok(n as f64),
+            ValueKind::BigInt(n) => Ok(n.to_f64()),
+            ValueKind::Fixed(n) => Ok(n.to_f64()),
+            ValueKind::Rational(n, d) => Ok(n as f64 / d as f64),
+            other => Err(other.conversion_error("decimal"))
+        }
+    }
+}
+
+impl TryFrom<ValueKind> for bool {
+    type Error = ConversionError;
+
+    fn try_from(other: ValueKind) -> Result<bool, ConversionError> {
+        match other {
+            ValueKind::Boolean(b) => Ok(b),
+            other => Err(other.conversion_error("boolean"))
+        }
+    }
+}
+
+impl TryFrom<ValueKind> for String {
+    type Error = ConversionError;
+
+    fn try_from(other: ValueKind) -> Result<String, ConversionError> {
+        match other {
+            ValueKind::Str(s) => Ok(s.to_string()),
+            other => Err(other.conversion_error("string"))
+        }
+    }
+}
+
+impl TryFrom<ValueKind> for Vec<u8> {
+    type Error = ConversionError;
+
+    fn try_from(other: ValueKind) -> Result<Vec<u8>, ConversionError> {
+        match other {
+            ValueKind::Bytes(bytes) => Ok(bytes.to_vec()),
+            other => Err(other.conversion_error("bytes"))
+        }
+    }
+}
+
+// Holds values that wait while other parts of an expression are evaluated,
+// like the left operand of a binary operator or the arguments of a call
 pub struct Stack<T> {
     max_size: usize,
     items: Vec<T>
@@ -43,249 +255,1423 @@ impl<T> Stack<T> {
         self.items.len()
     }
 
-    fn peek(&self) -> Option<&T> {
-        self.items.last()
+    fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.items.truncate(len);
+    }
+
+    // Everything on the stack, bottom first
+    fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    // Removes and returns everything from `at` up
+    fn split_off(&mut self, at: usize) -> Vec<T> {
+        self.items.split_off(at)
     }
 }
 
-struct Variable {
-    name: String,
-    value: ValueKind
+// What integer arithmetic does when the result doesn't fit into an i64
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowPolicy {
+    // Fail with a runtime error
+    #[default]
+    Error,
+    // Two's complement wrap around, like release builds of Rust
+    Wrap,
+    // Clamp to i64::MIN or i64::MAX
+    Saturate,
+    // Switch to a big integer, which goes back to being an integer once a
+    // result fits again
+    Promote
 }
 
-impl Variable {
-    fn new() -> Variable {
-        Variable {
-            name: String::new(),
-            value: ValueKind::None
+impl std::str::FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OverflowPolicy, String> {
+        match s {
+            "error" => Ok(OverflowPolicy::Error),
+            "wrap" => Ok(OverflowPolicy::Wrap),
+            "saturate" => Ok(OverflowPolicy::Saturate),
+            "promote" => Ok(OverflowPolicy::Promote),
+            other => Err(format!("Unknown overflow policy '{}', expected error, wrap, saturate or promote", other))
         }
     }
 }
 
+// How statements are evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EvalMode {
+    // Walking the tree recursively
+    #[default]
+    Tree,
+    // Compiled to postfix operations on the state's stack, see machine.rs
+    Stack
+}
+
+impl std::str::FromStr for EvalMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<EvalMode, String> {
+        match s {
+            "tree" => Ok(EvalMode::Tree),
+            "stack" => Ok(EvalMode::Stack),
+            other => Err(format!("Unknown evaluation mode '{}', expected tree or stack", other))
+        }
+    }
+}
+
+impl OverflowPolicy {
+    fn apply<F, G>(self, checked: Option<i64>, wrapped: i64, saturated: i64, expr: F, promoted: G) -> OpResult
+    where F: Fn() -> String, G: Fn() -> OpResult {
+        match (checked, self) {
+            (Some(n), _) => Ok(ValueKind::Integer(n)),
+            (None, OverflowPolicy::Error) => Err(RuntimeErrorKind::Overflow(expr())),
+            (None, OverflowPolicy::Wrap) => Ok(ValueKind::Integer(wrapped)),
+            (None, OverflowPolicy::Saturate) => Ok(ValueKind::Integer(saturated)),
+            (None, OverflowPolicy::Promote) => promoted()
+        }
+    }
+}
+
+// Bounds on a single call to `interpret`, for running untrusted code. An
+// operation is the evaluation of one node
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Limits {
+    pub max_ops: Option<u64>,
+    pub max_duration: Option<Duration>
+}
+
+// The limit that stopped an evaluation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Operations(u64),
+    Duration(Duration)
+}
+
+// Stops a running evaluation from the outside, e.g. another thread or a
+// signal handler. Clones share the same flag. Once cancelled, evaluations
+// keep failing until the token is reset
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Gets notified around every node evaluation, e.g. by the debugger or tracer
+pub trait Hook: Send + Sync {
+    fn enter_node(&mut self, _node: &Node, _state: &mut State) {}
+    fn exit_node(&mut self, _node: &Node, _value: &ValueKind) {}
+    // Instead of exit_node when evaluating the node failed
+    fn fail_node(&mut self, _node: &Node, _error: &RuntimeError) {}
+}
+
+// A builtin implemented by the program embedding the interpreter
+pub type HostFn = Box<dyn Fn(&[ValueKind]) -> Result<ValueKind, Error> + Send + Sync>;
+
+// The variables of a state at some point, see `State::snapshot`. Symbols
+// are never forgotten, so the slots still line up when it is restored
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    variables: Vec<Option<ValueKind>>
+}
+
+// Gets told what a script does as it runs, one event at a time. Unlike a
+// Hook, which sees every node, the events are about the program: statements,
+// variables and calls. Every method does nothing by default
+pub trait EvalObserver: Send + Sync {
+    fn statement_start(&mut self, _statement: &Node) {}
+    fn statement_end(&mut self, _statement: &Node, _value: &ValueKind) {}
+    fn variable_read(&mut self, _name: &str, _value: &ValueKind, _span: Span) {}
+    // `old` is None for a variable that didn't exist yet
+    fn variable_write(&mut self, _name: &str, _old: Option<&ValueKind>, _new: &ValueKind, _span: Span) {}
+    fn call(&mut self, _name: &str, _args: &[ValueKind], _span: Span) {}
+    fn call_return(&mut self, _name: &str, _value: &ValueKind) {}
+    // Any error that ends a call to `interpret`, including syntax errors
+    fn error(&mut self, _error: &Error) {}
+}
+
+// Told about every assignment a script makes, with the variable's name, its
+// previous value if it had one, and the new value
+pub type ChangeFn = Box<dyn Fn(&str, Option<&ValueKind>, &ValueKind) + Send + Sync>;
+
+struct ChangeObserver(ChangeFn);
+
+impl EvalObserver for ChangeObserver {
+    fn variable_write(&mut self, name: &str, old: Option<&ValueKind>, new: &ValueKind, _span: Span) {
+        (self.0)(name, old, new);
+    }
+}
+
+struct Builtin {
+    f: HostFn,
+    requires: Capabilities
+}
+
 pub struct State {
     pub stack: Stack<ValueKind>,
-    pub variables: HashMap<String, ValueKind>
+    // Every identifier the state has seen, so variables are keyed by symbol
+    pub symbols: Interner,
+    // The value of every variable in the slot its symbol indexes, so reading
+    // one doesn't need hashing
+    variables: Vec<Option<ValueKind>>,
+    functions: HashMap<String, Builtin>,
+    // What builtins are allowed to do, everything unless sandboxed
+    pub capabilities: Capabilities,
+    pub hook: Option<Box<dyn Hook>>,
+    pub overflow: OverflowPolicy,
+    pub eval_mode: EvalMode,
+    // Transformations applied to the tree before it runs
+    pub passes: PassManager,
+    // Turns implicit conversions into runtime errors
+    pub strict: bool,
+    pub limits: Limits,
+    // How deeply expressions may nest, see `set_max_call_depth`
+    max_depth: usize,
+    // Lets a prelude assign names under std., which is refused otherwise
+    in_prelude: bool,
+    // The stack as it was when the last run failed, see `stack_at_failure`
+    failed_stack: Option<Vec<ValueKind>>,
+    // Checked before every node, to interrupt long evaluations
+    pub cancel: CancelToken,
+    observers: Vec<Box<dyn EvalObserver>>,
+    // Progress of the current evaluation, checked against the limits
+    ops: u64,
+    depth: usize,
+    started: Instant
 }
 
+// Enough variables for most scripts, so the tables don't have to grow
+const INITIAL_VARIABLES: usize = 64;
+
+// How deeply expressions nest by default. Parsing, the passes and the tree
+// walker all recurse, and this fits in the 2 MiB stack of a spawned thread
+// even in a debug build
+pub const DEFAULT_MAX_DEPTH: usize = 200;
+
+// How many values an expression can have waiting on the stack by default
+pub const DEFAULT_STACK_SIZE: usize = 200;
+
+// How many of the innermost frames a depth error lists
+const REPORTED_FRAMES: usize = 4;
+
+// The standard library, which `State::new` runs before anything else
+pub const PRELUDE: &str = include_str!("interpreter/prelude.term");
+
+// What every name the prelude defines starts with
+pub const PRELUDE_NAMESPACE: &str = "std.";
+
 impl State {
     pub fn new() -> State {
-        State {
-            stack: Stack::with_capacity(200),
-            variables: HashMap::new()
+        let mut state = State::with_stack_size(DEFAULT_STACK_SIZE);
+        state.load_prelude();
+        state
+    }
+
+    // A state without the prelude, which `load_prelude` can add later
+    pub fn with_stack_size(stack_size: usize) -> State {
+        let mut state = State {
+            stack: Stack::with_capacity(stack_size),
+            symbols: Interner::with_capacity(INITIAL_VARIABLES),
+            variables: Vec::with_capacity(INITIAL_VARIABLES),
+            functions: HashMap::new(),
+            capabilities: Capabilities::ALL,
+            hook: None,
+            overflow: OverflowPolicy::default(),
+            eval_mode: EvalMode::default(),
+            passes: PassManager::for_level(1),
+            strict: false,
+            limits: Limits::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            in_prelude: false,
+            failed_stack: None,
+            cancel: CancelToken::default(),
+            observers: Vec::new(),
+            ops: 0,
+            depth: 0,
+            started: Instant::now()
+        };
+
+        register_builtins(&mut state);
+        state
+    }
+
+    // Runs the standard prelude into this state. It only assigns constants,
+    // so it can't fail
+    pub fn load_prelude(&mut self) {
+        self.set_constants();
+        self.run_prelude(PRELUDE).expect("the prelude failed to run");
+    }
+
+    // Infinity and NaN have no literal, so no prelude source can define
+    // them. Every prelude, even an empty one, starts with these
+    pub fn set_constants(&mut self) {
+        self.set_var("std.inf", ValueKind::Decimal(f64::INFINITY));
+        self.set_var("std.nan", ValueKind::Decimal(f64::NAN));
+    }
+
+    // Runs `source` as a prelude, which unlike other code may assign names
+    // under std.
+    pub fn run_prelude(&mut self, source: &str) -> Result<ValueKind, Error> {
+        let in_prelude = std::mem::replace(&mut self.in_prelude, true);
+        let result = interpret(source, self);
+        self.in_prelude = in_prelude;
+        result
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<&ValueKind> {
+        self.symbols.lookup(name).and_then(|symbol| self.slot(symbol))
+    }
+
+    pub fn set_var(&mut self, name: &str, value: ValueKind) {
+        let symbol = self.symbols.intern(name);
+        self.store(symbol, value);
+    }
+
+    // Every variable with its name, in no particular order
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &ValueKind)> {
+        self.symbols.iter().filter_map(|(symbol, name)| self.slot(symbol).map(|value| (name, value)))
+    }
+
+    fn slot(&self, symbol: Symbol) -> Option<&ValueKind> {
+        self.variables.get(symbol.index()).and_then(Option::as_ref)
+    }
+
+    fn store(&mut self, symbol: Symbol, value: ValueKind) {
+        if self.variables.len() <= symbol.index() {
+            self.variables.resize(symbol.index() + 1, None);
+        }
+        self.variables[symbol.index()] = Some(value);
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // Every nested expression is evaluated in a frame of its own, so this
+    // bounds how deeply they can nest. Deeper source is a syntax error, and
+    // trees a pass made deeper stop the evaluation. Far above the default,
+    // parsing and the passes can overflow the native stack again
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    // Observers are told about events in the order they were added
+    pub fn add_observer(&mut self, observer: Box<dyn EvalObserver>) {
+        self.observers.push(observer);
+    }
+
+    // Calls `f` after every assignment in a script. Variables set by the
+    // host don't count
+    pub fn on_change<F>(&mut self, f: F) where F: Fn(&str, Option<&ValueKind>, &ValueKind) + Send + Sync + 'static {
+        self.add_observer(Box::new(ChangeObserver(Box::new(f))));
+    }
+
+    // Tells every observer about an event. They are taken out meanwhile, so
+    // the rest of the state can be looked at to describe the event
+    fn notify<F>(&mut self, mut event: F) where F: FnMut(&mut dyn EvalObserver, &State) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in &mut observers {
+            event(observer.as_mut(), self);
+        }
+        self.observers = observers;
+    }
+
+    // Makes `f` callable as `name(...)`, replacing any function of that name
+    pub fn register_fn<F>(&mut self, name: &str, f: F) where F: Fn(&[ValueKind]) -> Result<ValueKind, Error> + Send + Sync + 'static {
+        self.register_fn_requiring(name, Capabilities::NONE, f);
+    }
+
+    // Like `register_fn` for a builtin with side effects. Calls fail unless
+    // the state grants everything in `requires`
+    pub fn register_fn_requiring<F>(&mut self, name: &str, requires: Capabilities, f: F)
+    where F: Fn(&[ValueKind]) -> Result<ValueKind, Error> + Send + Sync + 'static {
+        self.functions.insert(name.to_string(), Builtin { f: Box::new(f), requires });
+    }
+
+    // The names of every registered function, in no particular order
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    pub fn call_function(&self, name: &str, args: &[ValueKind]) -> OpResult {
+        let builtin = self.functions.get(name).ok_or_else(|| RuntimeErrorKind::UndefinedFunction(name.to_string()))?;
+
+        if !self.capabilities.contains(builtin.requires) {
+            let missing = builtin.requires.missing_from(self.capabilities);
+            return Err(RuntimeErrorKind::CapabilityDenied(name.to_string(), missing));
+        }
+
+        // Runtime errors, like being interrupted, keep their kind
+        (builtin.f)(args).map_err(|e| match e {
+            Error::Runtime(e) => e.kind,
+            e => RuntimeErrorKind::Host(name.to_string(), e.to_string())
+        })
+    }
+
+    // A copy of every variable, to go back to with `restore`. Strings are
+    // shared, so this is cheap
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { variables: self.variables.clone() }
+    }
+
+    // Puts every variable back the way it was at the snapshot, forgetting
+    // variables created since. The configuration is left alone
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.variables = snapshot.variables;
+    }
+
+    // The operands waiting on the stack, bottom first. Left sides of binary
+    // operators wait here for their right sides, and call arguments until
+    // the call, so this is only non-empty from a hook or the debugger
+    pub fn stack_contents(&self) -> &[ValueKind] {
+        self.stack.items()
+    }
+
+    // The operands that were waiting on the stack when the last run failed
+    // while evaluating, bottom first, or None if it didn't. They show how
+    // far the failed expression got
+    pub fn stack_at_failure(&self) -> Option<&[ValueKind]> {
+        self.failed_stack.as_deref()
+    }
+
+    // Keeps the stack for `stack_at_failure`, where the error happened.
+    // Outer frames fail with the same error later, once they have popped
+    // their operands, so only the first call counts
+    fn keep_failed_stack(&mut self) {
+        if self.failed_stack.is_none() {
+            self.failed_stack = Some(self.stack.items().to_vec());
         }
     }
 
-    fn push_stack(&mut self, item: ValueKind) {
-        self.stack.push(item);
+    // Forgets all variables and stack contents, keeping the configuration
+    // (stack size, hook and host functions) intact
+    pub fn reset(&mut self) {
+        self.variables.clear();
+        self.stack.clear();
     }
 
-    fn pop_stack(&mut self) {
-        self.stack.pop();
+    // Counts one more operation, failing once a limit is crossed or the
+    // evaluation is cancelled. The clock is only read every so often, as
+    // that is much slower than counting
+    fn tick(&mut self) -> Result<(), RuntimeErrorKind> {
+        self.ops += 1;
+
+        if self.cancel.is_cancelled() {
+            return Err(RuntimeErrorKind::Interrupted);
+        }
+
+        if let Some(max_ops) = self.limits.max_ops {
+            if self.ops > max_ops {
+                return Err(RuntimeErrorKind::LimitExceeded(Limit::Operations(max_ops)));
+            }
+        }
+
+        if let Some(max_duration) = self.limits.max_duration {
+            if self.ops.is_multiple_of(256) && self.started.elapsed() > max_duration {
+                return Err(RuntimeErrorKind::LimitExceeded(Limit::Duration(max_duration)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_stack(&mut self, item: ValueKind) -> Result<(), RuntimeErrorKind> {
+        if self.stack.push(item) {
+            Ok(())
+        } else {
+            Err(RuntimeErrorKind::StackOverflow(self.stack.max_size))
+        }
+    }
+
+    fn pop_stack(&mut self) -> ValueKind {
+        self.stack.pop().unwrap_or(ValueKind::None)
     }
 
     fn size_stack(&self) -> usize {
         self.stack.size()
     }
+}
 
-    fn peek_stack(&self) -> Option<&ValueKind> {
-        self.stack.peek()
+// The text of a value for str and print, which is its Display form except
+// that a string is its text without quotes
+fn text(value: &ValueKind) -> String {
+    match value {
+        ValueKind::Str(s) => s.to_string(),
+        other => other.to_string()
     }
 }
 
-fn visit_node(node: &Node, state: &mut State) -> ValueKind {
-    if node.children.len() == 0 {
-        return visit_alone_node(node)
+// The functions every state starts with
+fn register_builtins(state: &mut State) {
+    state.register_fn("str", |args| match args {
+        [value] => Ok(ValueKind::from(text(value))),
+        _ => Err(Error::Io(format!("Expected 1 argument, but got {}", args.len())))
+    });
+
+    // Writes the arguments to stdout separated by spaces, on a line
+    state.register_fn("print", |args| {
+        let texts: Vec<String> = args.iter().map(text).collect();
+        println!("{}", texts.join(" "));
+        Ok(ValueKind::None)
+    });
+
+    // The next line of stdin without its line ending, or none at the end
+    state.register_fn("readline", |args| {
+        if !args.is_empty() {
+            return Err(Error::Io(format!("Expected 0 arguments, but got {}", args.len())));
+        }
+
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) => Ok(ValueKind::None),
+            Ok(_) => {
+                let end = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(end);
+                Ok(ValueKind::from(line))
+            },
+            Err(e) => Err(Error::Io(format!("Can't read stdin: {}", e)))
+        }
+    });
+
+    // The closest decimal to any number, e.g. to see what a rational is
+    state.register_fn("to_float", |args| match args {
+        [value] => f64::try_from(value.clone()).map(ValueKind::Decimal).map_err(|e| Error::Io(e.to_string())),
+        _ => Err(Error::Io(format!("Expected 1 argument, but got {}", args.len())))
+    });
+
+    tcp::register(state);
+    bytes::register(state);
+    strings::register(state);
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::new()
+    }
+}
+
+// Which operand of a binary operator something is about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Left => write!(f, "left"),
+            Side::Right => write!(f, "right")
+        }
     }
+}
 
-    if let TokenKind::Plus | TokenKind::Minus | TokenKind::Asterisk | TokenKind::ForwardSlash | TokenKind::Assign | TokenKind::IsEquals | TokenKind::NotEquals = node.entry {
+// An operand of `op` had a type the operator can't work with
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub op: TokenKind,
+    pub side: Side,
+    pub expected: &'static str,
+    pub found: &'static str
+}
+
+impl TypeError {
+    fn operand(op: &TokenKind, side: Side, value: &ValueKind) -> RuntimeErrorKind {
+        RuntimeErrorKind::Type(TypeError {
+            op: op.clone(),
+            side,
+            expected: "integer or decimal",
+            found: value.type_name()
+        })
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected {} on the {} of `{}`, but found {}", self.expected, self.side, self.op, self.found)
+    }
+}
+
+// Errors that can happen while evaluating an already parsed statement
+#[derive(Debug, Clone)]
+pub enum RuntimeErrorKind {
+    // The name, and the closest existing name if one is similar enough
+    UndefinedVariable(String, Option<String>),
+    UndefinedFunction(String),
+    // A host function failed, with its name and what went wrong
+    Host(String, String),
+    DivisionByZero(String),
+    // The integer result of the expression doesn't fit into 64 bits
+    Overflow(String),
+    UnsupportedOperation(TokenKind),
+    Type(TypeError),
+    InvalidAssignment(ValueKind),
+    UnexpectedNode(TokenKind),
+    // An integer met a decimal in strict mode
+    MixedNumbers(ValueKind, ValueKind),
+    // The expression needs more stack slots than the state has
+    StackOverflow(usize),
+    // Expressions nest more deeply than the state allows. Holds the limit
+    // and the innermost few frames, innermost first
+    TooDeep(usize, Vec<String>),
+    LimitExceeded(Limit),
+    // A builtin needs capabilities the state doesn't grant
+    CapabilityDenied(String, Capabilities),
+    // A % template doesn't fit its value
+    Format(String),
+    // Names under std. are left to the prelude
+    ReadOnly(String),
+    // The state's cancel token was triggered
+    Interrupted
+}
+
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::UndefinedVariable(name, None) => write!(f, "No such variable: {:?}", name),
+            RuntimeErrorKind::UndefinedVariable(name, Some(closest)) => {
+                write!(f, "No such variable: {:?}, did you mean {:?}?", name, closest)
+            },
+            RuntimeErrorKind::UndefinedFunction(name) => write!(f, "No such function: {:?}", name),
+            RuntimeErrorKind::Host(name, message) => write!(f, "Error in {}: {}", name, message),
+            RuntimeErrorKind::DivisionByZero(expr) => write!(f, "Can't divide by zero: {}", expr),
+            RuntimeErrorKind::Overflow(expr) => write!(f, "Integer overflow: {}", expr),
+            RuntimeErrorKind::UnsupportedOperation(op) => write!(f, "Unexpected operation: `{}`", op),
+            RuntimeErrorKind::Type(e) => write!(f, "{}", e),
+            RuntimeErrorKind::InvalidAssignment(lhs) => write!(f, "Expected identifier on the left side, but got: {}", lhs),
+            RuntimeErrorKind::UnexpectedNode(entry) => write!(f, "Unexpected node type: `{}`", entry),
+            RuntimeErrorKind::MixedNumbers(lhs, rhs) => {
+                write!(f, "Can't mix integer and decimal in strict mode: {} and {}", lhs, rhs)
+            },
+            RuntimeErrorKind::StackOverflow(size) => write!(f, "Stack overflow: the expression needs more than {} stack slots", size),
+            RuntimeErrorKind::TooDeep(depth, frames) => {
+                write!(f, "Expressions nest more than {} deep, innermost: {}", depth, frames.join(", "))
+            },
+            RuntimeErrorKind::LimitExceeded(Limit::Operations(n)) => write!(f, "Execution limit exceeded: more than {} operations", n),
+            RuntimeErrorKind::LimitExceeded(Limit::Duration(d)) => write!(f, "Execution limit exceeded: ran longer than {:?}", d),
+            RuntimeErrorKind::CapabilityDenied(name, missing) => write!(f, "{} is not allowed here, it needs: {}", name, missing),
+            RuntimeErrorKind::Format(message) => write!(f, "Can't format: {}", message),
+            RuntimeErrorKind::ReadOnly(name) => write!(f, "Can't assign `{}`, names under {} belong to the prelude", name, PRELUDE_NAMESPACE),
+            RuntimeErrorKind::Interrupted => write!(f, "Interrupted")
+        }
+    }
+}
+
+impl RuntimeErrorKind {
+    // A stable name for the error, for tools that read diagnostics
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeErrorKind::UndefinedVariable(..) => "undefined-variable",
+            RuntimeErrorKind::UndefinedFunction(_) => "undefined-function",
+            RuntimeErrorKind::Host(..) => "host-error",
+            RuntimeErrorKind::DivisionByZero(_) => "division-by-zero",
+            RuntimeErrorKind::Overflow(_) => "overflow",
+            RuntimeErrorKind::UnsupportedOperation(_) => "unsupported-operation",
+            RuntimeErrorKind::Type(_) => "type-error",
+            RuntimeErrorKind::InvalidAssignment(_) => "invalid-assignment",
+            RuntimeErrorKind::UnexpectedNode(_) => "unexpected-node",
+            RuntimeErrorKind::MixedNumbers(..) => "mixed-numbers",
+            RuntimeErrorKind::StackOverflow(_) => "stack-overflow",
+            RuntimeErrorKind::TooDeep(..) => "too-deep",
+            RuntimeErrorKind::LimitExceeded(_) => "limit-exceeded",
+            RuntimeErrorKind::CapabilityDenied(..) => "capability-denied",
+            RuntimeErrorKind::Format(_) => "format-error",
+            RuntimeErrorKind::ReadOnly(_) => "read-only",
+            RuntimeErrorKind::Interrupted => "interrupted"
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+// Everything that can go wrong while interpreting a piece of source
+#[derive(Debug, Clone)]
+pub enum Error {
+    Lex(LexError),
+    Syntax(Vec<ParseError>),
+    Runtime(RuntimeError),
+    // The source couldn't be read, e.g. a missing file
+    Io(String)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Lex(e) => write!(f, "{}", e),
+            Error::Syntax(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            },
+            Error::Runtime(e) => write!(f, "{}", e),
+            Error::Io(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<LexError> for Error {
+    fn from(other: LexError) -> Error {
+        Error::Lex(other)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(other: RuntimeError) -> Error {
+        Error::Runtime(other)
+    }
+}
+
+type EvalResult = Result<ValueKind, RuntimeError>;
+type OpResult = Result<ValueKind, RuntimeErrorKind>;
+
+// Attaches a location to an error raised while evaluating `node`. A missing
+// variable or an operand of the wrong type is pointed at directly
+fn locate(kind: RuntimeErrorKind, node: &Node) -> RuntimeError {
+    let span = match &kind {
+        RuntimeErrorKind::UndefinedVariable(name, _) => node.children.iter()
+            .find(|c| matches!(&c.entry, TokenKind::Identifier(n) if n == name))
+            .map_or(node.span, |c| c.span),
+        RuntimeErrorKind::Type(e) => {
+            let operand = match e.side {
+                Side::Left => node.children.first(),
+                Side::Right => node.children.get(1)
+            };
+            operand.map_or(node.span, |c| c.span)
+        },
+        _ => node.span
+    };
+
+    RuntimeError { kind, span }
+}
+
+fn visit_node(node: &Node, state: &mut State) -> EvalResult {
+    let result = guarded_visit(node, state);
+    if result.is_err() {
+        state.keep_failed_stack();
+    }
+    result
+}
+
+// Visits the node unless a limit is crossed
+fn guarded_visit(node: &Node, state: &mut State) -> EvalResult {
+    state.tick().map_err(|e| locate(e, node))?;
+
+    if state.depth >= state.max_depth {
+        return Err(locate(RuntimeErrorKind::TooDeep(state.max_depth, vec![frame(node)]), node));
+    }
+
+    state.depth += 1;
+    let result = enter_node(node, state);
+    state.depth -= 1;
+
+    // Frames are collected on the way out, keeping the innermost
+    result.map_err(|mut e| {
+        if let RuntimeErrorKind::TooDeep(_, frames) = &mut e.kind {
+            if frames.len() < REPORTED_FRAMES {
+                frames.push(frame(node));
+            }
+        }
+        e
+    })
+}
+
+// A frame in a depth error, as the node's token and where it is
+fn frame(node: &Node) -> String {
+    format!("`{}` at {}", node.entry, node.span)
+}
+
+fn enter_node(node: &Node, state: &mut State) -> EvalResult {
+    // The hook is taken out while it runs, so it can freely use the state
+    if let Some(mut hook) = state.hook.take() {
+        hook.enter_node(node, state);
+        state.hook = Some(hook);
+    }
+
+    let result = eval_node(node, state);
+
+    if let Some(hook) = state.hook.as_mut() {
+        match &result {
+            Ok(value) => hook.exit_node(node, value),
+            Err(e) => hook.fail_node(node, e)
+        }
+    }
+
+    result
+}
+
+fn eval_node(node: &Node, state: &mut State) -> EvalResult {
+    if node.children.is_empty() {
+        return visit_alone_node(node, state)
+    }
+
+    if let TokenKind::Lparen = node.entry {
+        return visit_call_node(node, state)
+    }
+
+    if let TokenKind::Plus | TokenKind::Minus | TokenKind::Asterisk | TokenKind::ForwardSlash | TokenKind::Percent | TokenKind::Assign | TokenKind::IsEquals | TokenKind::NotEquals | TokenKind::Less | TokenKind::Greater = node.entry {
         if node.children.len() == 1 {
             visit_unaryop_node(node, state)
         } else if node.children.len() == 2 {
             visit_binop_node(node, state)
         } else {
-            panic!("Can't visit unexpected node!");
+            Err(locate(RuntimeErrorKind::UnexpectedNode(node.entry.clone()), node))
         }
     } else {
-        panic!("Unexpected node type: {:?}", node.entry);
+        Err(locate(RuntimeErrorKind::UnexpectedNode(node.entry.clone()), node))
     }
 }
 
-fn visit_alone_node(node: &Node) -> ValueKind {
-    match &node.entry {
-        TokenKind::Integer(n) => ValueKind::Integer(n.to_owned()),
-        TokenKind::Decimal(n) => ValueKind::Decimal(n.to_owned()),
-        TokenKind::Identifier(n) => ValueKind::Identifier(n.to_string()),
-        TokenKind::Boolean(b) => ValueKind::Boolean(b.to_owned()),
-        TokenKind::QuotedString(s) => ValueKind::Str(s.to_string()),
+// A variable is read right at its leaf, so operators only ever see values
+fn visit_alone_node(node: &Node, state: &mut State) -> EvalResult {
+    Ok(match &node.entry {
+        TokenKind::Integer(n) => ValueKind::Integer(*n),
+        TokenKind::Decimal(n) => ValueKind::Decimal(*n),
+        TokenKind::BigInt(n) => ValueKind::BigInt(n.clone()),
+        TokenKind::Fixed(n) => ValueKind::Fixed(n.clone()),
+        TokenKind::Identifier(n) => {
+            let symbol = node.symbol.unwrap_or_else(|| state.symbols.intern(n));
+            let value = get_var(symbol, state).map_err(|e| locate(e, node))?;
+            state.notify(|o, _| o.variable_read(n, &value, node.span));
+            value
+        },
+        TokenKind::Boolean(b) => ValueKind::Boolean(*b),
+        TokenKind::None => ValueKind::None,
+        TokenKind::QuotedString(s) => ValueKind::Str(Arc::from(s.as_str())),
+        TokenKind::Bytes(bytes) => ValueKind::Bytes(Arc::from(bytes.as_slice())),
         _ => ValueKind::None
-    }
+    })
 }
 
-fn do_number_node(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind, state: &mut State) -> ValueKind {
-    if let ValueKind::Identifier(n1) = &lhs {
-        if let ValueKind::Identifier(n2) = &rhs {
-            return do_self(&get_var(n1, state), &get_var(n2, state), op)
-        } else {
-            return do_self(&get_var(n1, state), rhs, op)
-        }
-    } else {
-        if let ValueKind::Identifier(n) = &rhs {
-            return do_self(lhs, &get_var(n, state), op)
-        }
+// The arithmetic and comparison rules for two values, shared by evaluation
+// and constant folding so both always agree
+fn do_operation(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind, state: &State) -> OpResult {
+    let overflow = state.overflow;
+
+    if state.strict && matches!(
+        (lhs, rhs),
+        (ValueKind::Integer(_) | ValueKind::BigInt(_) | ValueKind::Fixed(_) | ValueKind::Rational(..), ValueKind::Decimal(_)) |
+        (ValueKind::Decimal(_), ValueKind::Integer(_) | ValueKind::BigInt(_) | ValueKind::Fixed(_) | ValueKind::Rational(..))
+    ) {
+        return Err(RuntimeErrorKind::MixedNumbers(lhs.clone(), rhs.clone()))
     }
 
-    return do_self(lhs, rhs, op);
+    if let TokenKind::IsEquals | TokenKind::NotEquals | TokenKind::Less | TokenKind::Greater = op {
+        return do_comparison(lhs, rhs, op);
+    }
 
-    fn do_self(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind) -> ValueKind {
-        match &lhs {
-            &ValueKind::Decimal(ln) => {
-                match rhs {
-                    &ValueKind::Decimal(rn) => {
-                        match op {
-                            &TokenKind::Plus => ValueKind::Decimal(ln + rn),
-                            &TokenKind::Minus => ValueKind::Decimal(ln - rn),
-                            &TokenKind::Asterisk => ValueKind::Decimal(ln * rn),
-                            &TokenKind::ForwardSlash => {
-                                if rn != 0.0 {
-                                    ValueKind::Decimal(ln / rn)
-                                } else {
-                                    panic!("Can't divide by zero, {} / {}", ln, rn)
-                                }
-                            },
-                            &TokenKind::IsEquals => ValueKind::Boolean(ln.clone() == rn),
-                            _ => panic!("Unexpected operation: {:?}", op)
-                        }
-                    },
-                    &ValueKind::Integer(rn) => {
-                        match op {
-                            &TokenKind::Plus => ValueKind::Decimal(ln + rn as f64),
-                            &TokenKind::Minus => ValueKind::Decimal(ln - rn as f64),
-                            &TokenKind::Asterisk => ValueKind::Decimal(ln * rn as f64),
-                            &TokenKind::ForwardSlash => {
-                                if rn as f64 != 0.0 {
-                                    ValueKind::Decimal(ln / rn as f64)
-                                } else {
-                                    panic!("Can't divide by zero: {} / {}", ln, rn)
-                                }
-                            },
-                            &TokenKind::IsEquals => ValueKind::Boolean(ln.clone() == rn as f64),
-                            _ => panic!("Unexpected operation: {:?}", op)
-                        }
-                    },
-                    _ => panic!("Right value should be integer or float: {:?}!", rhs)
-                }
-            },
-            &ValueKind::Integer(ln) => {
-                match rhs {
-                    &ValueKind::Decimal(rn) => {
-                        match op {
-                            &TokenKind::Plus => ValueKind::Decimal(ln.clone() as f64 + rn),
-                            &TokenKind::Minus => ValueKind::Decimal(ln.clone() as f64 - rn),
-                            &TokenKind::Asterisk => ValueKind::Decimal(ln.clone() as f64 * rn),
-                            &TokenKind::ForwardSlash => {
-                                if rn != 0.0 {
-                                    ValueKind::Decimal(ln.clone() as f64 / rn)
-                                } else {
-                                    panic!("Can't divide by zero, {} / {}", ln, rn)
-                                }
-                            },
-                            &TokenKind::IsEquals => ValueKind::Boolean(ln.clone() as f64 == rn),
-                            _ => panic!("Unexpected operation: {:?}", op)
-                        }
-                    },
-                    &ValueKind::Integer(rn) => {
-                        match op {
-                            &TokenKind::Plus => ValueKind::Integer(ln + rn),
-                            &TokenKind::Minus => ValueKind::Integer(ln - rn),
-                            &TokenKind::Asterisk => ValueKind::Integer(ln * rn),
-                            &TokenKind::ForwardSlash => {
-                                if rn != 0 {
-                                    if ln % rn != 0 {
-                                        ValueKind::Decimal(ln.clone() as f64 / rn as f64)
-                                    } else {
-                                        ValueKind::Integer(ln.clone() / rn)
-                                    }
+    // Only strings have a meaning for %, as templates
+    if let TokenKind::Percent = op {
+        return match lhs {
+            ValueKind::Str(template) => format::format(template, rhs).map(ValueKind::from),
+            other => Err(RuntimeErrorKind::Type(TypeError { op: op.clone(), side: Side::Left, expected: "string", found: other.type_name() }))
+        };
+    }
+
+    if let Some(result) = do_fixed_operation(lhs, rhs, op, state) {
+        return result;
+    }
+
+    if let Some(result) = do_big_operation(lhs, rhs, op, state) {
+        return result;
+    }
+
+    if let Some(result) = do_rational_operation(lhs, rhs, op, state) {
+        return result;
+    }
+
+    let value = match lhs {
+        ValueKind::Decimal(ln) => {
+            match *rhs {
+                ValueKind::Decimal(rn) => {
+                    match *op {
+                        TokenKind::Plus => ValueKind::Decimal(ln + rn),
+                        TokenKind::Minus => ValueKind::Decimal(ln - rn),
+                        TokenKind::Asterisk => ValueKind::Decimal(ln * rn),
+                        TokenKind::ForwardSlash => {
+                            if rn != 0.0 {
+                                ValueKind::Decimal(ln / rn)
+                            } else {
+                                return Err(RuntimeErrorKind::DivisionByZero(format!("{} / {}", ln, rn)))
+                            }
+                        },
+                        _ => return Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+                    }
+                },
+                ValueKind::Integer(rn) => {
+                    match *op {
+                        TokenKind::Plus => ValueKind::Decimal(ln + rn as f64),
+                        TokenKind::Minus => ValueKind::Decimal(ln - rn as f64),
+                        TokenKind::Asterisk => ValueKind::Decimal(ln * rn as f64),
+                        TokenKind::ForwardSlash => {
+                            if rn as f64 != 0.0 {
+                                ValueKind::Decimal(ln / rn as f64)
+                            } else {
+                                return Err(RuntimeErrorKind::DivisionByZero(format!("{} / {}", ln, rn)))
+                            }
+                        },
+                        _ => return Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+                    }
+                },
+                _ => return Err(TypeError::operand(op, Side::Right, rhs))
+            }
+        },
+        ValueKind::Integer(ln) => {
+            match *rhs {
+                ValueKind::Decimal(rn) => {
+                    match *op {
+                        TokenKind::Plus => ValueKind::Decimal(*ln as f64 + rn),
+                        TokenKind::Minus => ValueKind::Decimal(*ln as f64 - rn),
+                        TokenKind::Asterisk => ValueKind::Decimal(*ln as f64 * rn),
+                        TokenKind::ForwardSlash => {
+                            if rn != 0.0 {
+                                ValueKind::Decimal(*ln as f64 / rn)
+                            } else {
+                                return Err(RuntimeErrorKind::DivisionByZero(format!("{} / {}", ln, rn)))
+                            }
+                        },
+                        _ => return Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+                    }
+                },
+                ValueKind::Integer(rn) => {
+                    match *op {
+                        TokenKind::Plus => overflow.apply(
+                            ln.checked_add(rn), ln.wrapping_add(rn), ln.saturating_add(rn), || format!("{} + {}", ln, rn), || promote(*ln, rn, op, state)
+                        )?,
+                        TokenKind::Minus => overflow.apply(
+                            ln.checked_sub(rn), ln.wrapping_sub(rn), ln.saturating_sub(rn), || format!("{} - {}", ln, rn), || promote(*ln, rn, op, state)
+                        )?,
+                        TokenKind::Asterisk => overflow.apply(
+                            ln.checked_mul(rn), ln.wrapping_mul(rn), ln.saturating_mul(rn), || format!("{} * {}", ln, rn), || promote(*ln, rn, op, state)
+                        )?,
+                        TokenKind::ForwardSlash => {
+                            if rn != 0 {
+                                if ln.wrapping_rem(rn) != 0 {
+                                    rational(*ln as i128, rn as i128).ok_or_else(|| RuntimeErrorKind::Overflow(format!("{} / {}", ln, rn)))?
                                 } else {
-                                    panic!("Can't divide by zero: {} / {}", ln, rn)
+                                    // Only i64::MIN / -1 can overflow here
+                                    overflow.apply(
+                                        ln.checked_div(rn), ln.wrapping_div(rn), ln.saturating_div(rn), || format!("{} / {}", ln, rn), || promote(*ln, rn, op, state)
+                                    )?
                                 }
-                            },
-                            &TokenKind::IsEquals => ValueKind::Boolean(ln.clone() == rn),
-                            _ => panic!("Unexpected operation: {:?}", op)
-                        }
-                    },
-                    _ => panic!("Right value should be integer or float: {:?}!", rhs)
-                }
+                            } else {
+                                return Err(RuntimeErrorKind::DivisionByZero(format!("{} / {}", ln, rn)))
+                            }
+                        },
+                        _ => return Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+                    }
+                },
+                _ => return Err(TypeError::operand(op, Side::Right, rhs))
+            }
+        },
+        _ => return Err(TypeError::operand(op, Side::Left, lhs))
+    };
+
+    Ok(value)
+}
+
+// Any two values can be checked for equality, but only values of the same
+// kind can be put in order, with all numbers counting as one kind. The
+// rules are in compare.rs
+fn do_comparison(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind) -> OpResult {
+    let value = match op {
+        TokenKind::IsEquals => lhs == rhs,
+        TokenKind::NotEquals => lhs != rhs,
+        _ if !compare::comparable(lhs, rhs) => {
+            let expected = if compare::is_number(lhs) { "integer or decimal" } else { lhs.type_name() };
+            return Err(RuntimeErrorKind::Type(TypeError { op: op.clone(), side: Side::Right, expected, found: rhs.type_name() }))
+        },
+        TokenKind::Less => lhs < rhs,
+        _ => lhs > rhs
+    };
+
+    Ok(ValueKind::Boolean(value))
+}
+
+// An integer as an exact decimal, if it fits
+fn to_fixed(n: &BigInt) -> Result<Fixed, RuntimeErrorKind> {
+    n.to_string().parse().map_err(|_| RuntimeErrorKind::Overflow(format!("{} as an exact decimal", n)))
+}
+
+// A rational as an exact decimal, rounded like a division
+fn rational_to_fixed(n: i64, d: i64) -> Result<Fixed, RuntimeErrorKind> {
+    Fixed::from(n).checked_div(Fixed::from(d)).ok_or_else(|| RuntimeErrorKind::Overflow(format!("{}/{} as an exact decimal", n, d)))
+}
+
+// The exact result of an integer operation that overflowed, for
+// OverflowPolicy::Promote
+fn promote(ln: i64, rn: i64, op: &TokenKind, state: &State) -> OpResult {
+    do_operation(&ValueKind::from(BigInt::from(ln)), &ValueKind::Integer(rn), op, state)
+}
+
+// Arithmetic where either side is an exact decimal, or None when neither is.
+// Integers of either kind are converted exactly and rationals are rounded,
+// while a decimal turns the exact decimal into a decimal too
+fn do_fixed_operation(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind, state: &State) -> Option<OpResult> {
+    let operands = match (lhs, rhs) {
+        (ValueKind::Fixed(ln), ValueKind::Fixed(rn)) => Ok((**ln, **rn)),
+        (ValueKind::Fixed(ln), ValueKind::Integer(rn)) => Ok((**ln, Fixed::from(*rn))),
+        (ValueKind::Integer(ln), ValueKind::Fixed(rn)) => Ok((Fixed::from(*ln), **rn)),
+        (ValueKind::Fixed(ln), ValueKind::BigInt(rn)) => to_fixed(rn).map(|rn| (**ln, rn)),
+        (ValueKind::BigInt(ln), ValueKind::Fixed(rn)) => to_fixed(ln).map(|ln| (ln, **rn)),
+        (ValueKind::Fixed(ln), ValueKind::Rational(n, d)) => rational_to_fixed(*n, *d).map(|rn| (**ln, rn)),
+        (ValueKind::Rational(n, d), ValueKind::Fixed(rn)) => rational_to_fixed(*n, *d).map(|ln| (ln, **rn)),
+        (ValueKind::Fixed(ln), ValueKind::Decimal(_)) => return Some(do_operation(&ValueKind::Decimal(ln.to_f64()), rhs, op, state)),
+        (ValueKind::Decimal(_), ValueKind::Fixed(rn)) => return Some(do_operation(lhs, &ValueKind::Decimal(rn.to_f64()), op, state)),
+        (ValueKind::Fixed(_), _) => return Some(Err(TypeError::operand(op, Side::Right, rhs))),
+        _ => return None
+    };
+
+    let (ln, rn) = match operands {
+        Ok(operands) => operands,
+        Err(e) => return Some(Err(e))
+    };
+    let overflow = |symbol: &str| RuntimeErrorKind::Overflow(format!("{} {} {}", ln, symbol, rn));
+
+    Some(match *op {
+        TokenKind::Plus => ln.checked_add(rn).map(ValueKind::from).ok_or_else(|| overflow("+")),
+        TokenKind::Minus => ln.checked_sub(rn).map(ValueKind::from).ok_or_else(|| overflow("-")),
+        TokenKind::Asterisk => ln.checked_mul(rn).map(ValueKind::from).ok_or_else(|| overflow("*")),
+        TokenKind::ForwardSlash if rn.is_zero() => Err(RuntimeErrorKind::DivisionByZero(format!("{} / {}", ln, rn))),
+        TokenKind::ForwardSlash => ln.checked_div(rn).map(ValueKind::from).ok_or_else(|| overflow("/")),
+        _ => Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+    })
+}
+
+// Arithmetic where either side is a big integer, or None when neither is.
+// An integer on the other side is widened, so the result stays big, while
+// a decimal turns the big integer into a decimal too. Next to a rational, a
+// big integer that fits is treated like an integer, and any other like a
+// decimal
+fn do_big_operation(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind, state: &State) -> Option<OpResult> {
+    let (ln, rn) = match (lhs, rhs) {
+        (ValueKind::BigInt(ln), ValueKind::BigInt(rn)) => ((**ln).clone(), (**rn).clone()),
+        (ValueKind::BigInt(ln), ValueKind::Integer(rn)) => ((**ln).clone(), BigInt::from(*rn)),
+        (ValueKind::Integer(ln), ValueKind::BigInt(rn)) => (BigInt::from(*ln), (**rn).clone()),
+        (ValueKind::BigInt(ln), ValueKind::Decimal(_)) => return Some(do_operation(&ValueKind::Decimal(ln.to_f64()), rhs, op, state)),
+        (ValueKind::Decimal(_), ValueKind::BigInt(rn)) => return Some(do_operation(lhs, &ValueKind::Decimal(rn.to_f64()), op, state)),
+        (ValueKind::BigInt(ln), ValueKind::Rational(..)) => {
+            let ln = ln.to_i64().map_or(ValueKind::Decimal(ln.to_f64()), ValueKind::Integer);
+            return Some(do_operation(&ln, rhs, op, state))
+        },
+        (ValueKind::Rational(..), ValueKind::BigInt(rn)) => {
+            let rn = rn.to_i64().map_or(ValueKind::Decimal(rn.to_f64()), ValueKind::Integer);
+            return Some(do_operation(lhs, &rn, op, state))
+        },
+        (ValueKind::BigInt(_), _) => return Some(Err(TypeError::operand(op, Side::Right, rhs))),
+        _ => return None
+    };
+
+    // When big integers only exist to avoid overflow, results that fit are
+    // integers again
+    let big = |n: BigInt| match n.to_i64() {
+        Some(n) if state.overflow == OverflowPolicy::Promote => ValueKind::Integer(n),
+        _ => ValueKind::from(n)
+    };
+
+    Some(match *op {
+        TokenKind::Plus => Ok(big(&ln + &rn)),
+        TokenKind::Minus => Ok(big(&ln - &rn)),
+        TokenKind::Asterisk => Ok(big(&ln * &rn)),
+        // Rationals only hold 64-bit integers, so an uneven division is a
+        // rational when the fraction in lowest terms fits, and an overflow
+        // otherwise, rather than silently losing precision
+        TokenKind::ForwardSlash => match ln.div_rem(&rn) {
+            Some((quotient, remainder)) if remainder.is_zero() => Ok(big(quotient)),
+            Some(_) => {
+                let divisor = ln.gcd(&rn);
+                let reduced = |n: &BigInt| n.div_rem(&divisor).and_then(|(n, _)| n.to_i64());
+                reduced(&ln).zip(reduced(&rn))
+                    .and_then(|(n, d)| rational(n as i128, d as i128))
+                    .ok_or_else(|| RuntimeErrorKind::Overflow(format!("{} / {}", ln, rn)))
             },
-            _ => panic!("Left value should be integer or float: {:?}!", lhs)
-        }
+            None => Err(RuntimeErrorKind::DivisionByZero(format!("{} / {}", ln, rn)))
+        },
+        _ => Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+    })
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
     }
+    a
 }
 
-fn get_var(name: &String, state: &mut State) -> ValueKind {
-    let new_value = state.variables.get(name).expect(format!("No such variable: {:?}", name).as_str());
+// The fraction in lowest terms with a positive denominator, which is an
+// integer when the denominator comes out as 1. None when it doesn't fit
+// into 64 bits. The denominator can't be zero
+fn rational(numerator: i128, denominator: i128) -> Option<ValueKind> {
+    let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs()) as i128 * denominator.signum();
+    let n = i64::try_from(numerator / divisor).ok()?;
+    let d = i64::try_from(denominator / divisor).ok()?;
 
-    match &new_value {
-        &ValueKind::Decimal(v) => ValueKind::Decimal(v.to_owned()),
-        &ValueKind::Integer(v) => ValueKind::Integer(v.to_owned()),
-        &ValueKind::Str(v) => ValueKind::Str(v.to_string()),
-        &ValueKind::Boolean(v) => ValueKind::Boolean(v.to_owned()),
-        _ => ValueKind::None
+    Some(if d == 1 { ValueKind::Integer(n) } else { ValueKind::Rational(n, d) })
+}
+
+// Arithmetic where either side is a rational, or None when neither is. With
+// an integer the result is exact, and normalized back to an integer where
+// possible, so 1 / 3 * 3 is 1. A decimal turns the rational into a decimal
+fn do_rational_operation(lhs: &ValueKind, rhs: &ValueKind, op: &TokenKind, state: &State) -> Option<OpResult> {
+    let ((ln, ld), (rn, rd)) = match (lhs, rhs) {
+        (ValueKind::Rational(ln, ld), ValueKind::Rational(rn, rd)) => ((*ln, *ld), (*rn, *rd)),
+        (ValueKind::Rational(ln, ld), ValueKind::Integer(rn)) => ((*ln, *ld), (*rn, 1)),
+        (ValueKind::Integer(ln), ValueKind::Rational(rn, rd)) => ((*ln, 1), (*rn, *rd)),
+        (ValueKind::Rational(n, d), ValueKind::Decimal(_)) => return Some(do_operation(&ValueKind::Decimal(*n as f64 / *d as f64), rhs, op, state)),
+        (ValueKind::Decimal(_), ValueKind::Rational(n, d)) => return Some(do_operation(lhs, &ValueKind::Decimal(*n as f64 / *d as f64), op, state)),
+        (ValueKind::Rational(..), _) => return Some(Err(TypeError::operand(op, Side::Right, rhs))),
+        _ => return None
+    };
+
+    // Products of two i64 always fit, and so do sums of two such products
+    let (ln, ld, rn, rd) = (ln as i128, ld as i128, rn as i128, rd as i128);
+    let show = |n: i128, d: i128| if d == 1 { n.to_string() } else { format!("{}/{}", n, d) };
+    let expr = |symbol: &str| format!("{} {} {}", show(ln, ld), symbol, show(rn, rd));
+    // Wrapping or clamping a fraction gives a number unrelated to the exact
+    // one, so a result that doesn't fit is an overflow under every policy,
+    // except that Promote turns a whole number into a big integer
+    let exact = |n: i128, d: i128, symbol: &str| match rational(n, d) {
+        Some(value) => Ok(value),
+        None if state.overflow == OverflowPolicy::Promote && n % d == 0 => Ok(ValueKind::from(BigInt::from(n / d))),
+        None => Err(RuntimeErrorKind::Overflow(expr(symbol)))
+    };
+
+    Some(match *op {
+        TokenKind::Plus => exact(ln * rd + rn * ld, ld * rd, "+"),
+        TokenKind::Minus => exact(ln * rd - rn * ld, ld * rd, "-"),
+        TokenKind::Asterisk => exact(ln * rn, ld * rd, "*"),
+        TokenKind::ForwardSlash if rn == 0 => Err(RuntimeErrorKind::DivisionByZero(expr("/"))),
+        TokenKind::ForwardSlash => exact(ln * rd, ld * rn, "/"),
+        _ => Err(RuntimeErrorKind::UnsupportedOperation(op.clone()))
+    })
+}
+
+// Copying a value out is cheap, as strings are shared
+fn get_var(symbol: Symbol, state: &State) -> OpResult {
+    state.slot(symbol).cloned().ok_or_else(|| {
+        let name = state.symbols.resolve(symbol);
+        RuntimeErrorKind::UndefinedVariable(name.to_string(), closest_name(name, state))
+    })
+}
+
+// Levenshtein distance, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
     }
+
+    previous[b.len()]
 }
 
-fn do_assign_node(lhs: &ValueKind, rhs: &ValueKind, state: &mut State) -> ValueKind {
-    if let ValueKind::Identifier(name) = lhs {
-        let mut new_var = Variable::new();
-        new_var.name = name.to_string();
-        
-        match rhs {
-            ValueKind::Decimal(n) => { new_var.value = ValueKind::Decimal(n.to_owned()); },
-            ValueKind::Integer(n) => { new_var.value = ValueKind::Integer(n.to_owned()); },
-            ValueKind::Str(n) => { new_var.value = ValueKind::Str(n.to_string()); },
-            ValueKind::Identifier(n) => { new_var.value = get_var(n, state); },
-            ValueKind::Boolean(n) => { new_var.value = ValueKind::Boolean(n.to_owned()) },
-            _ => { new_var.value = ValueKind::None; }
+// The existing variable most likely meant instead of `name`, allowing roughly
+// one typo per three characters
+fn closest_name(name: &str, state: &State) -> Option<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    state.vars()
+        .map(|(candidate, _)| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+// The target of an assignment is a name, so it isn't evaluated like an
+// operand. Anything else as a target is only evaluated to report it
+fn visit_assign_node(node: &Node, state: &mut State) -> EvalResult {
+    let target = &node.children[0];
+
+    let symbol = match &target.entry {
+        TokenKind::Identifier(name) => writable(name, target, state)?,
+        _ => {
+            let lhs = visit_node(target, state)?;
+            return Err(locate(RuntimeErrorKind::InvalidAssignment(lhs), node))
         }
+    };
 
-        let v = &new_var.value.to_owned();
-        state.variables.insert(new_var.name, new_var.value);
+    let value = visit_node(&node.children[1], state)?;
+    assign(symbol, value, target, state)
+}
 
-        v.to_owned()
-        
+// The symbol to assign `name` to. Only a prelude may assign names in its
+// namespace
+fn writable(name: &str, target: &Node, state: &mut State) -> Result<Symbol, RuntimeError> {
+    if !state.in_prelude && name.starts_with(PRELUDE_NAMESPACE) {
+        Err(locate(RuntimeErrorKind::ReadOnly(name.to_string()), target))
     } else {
-        panic!("Expected identifier on the left side, but got: {:?}", lhs)
+        Ok(target.symbol.unwrap_or_else(|| state.symbols.intern(name)))
     }
 }
 
-fn visit_binop_node(node: &Node, state: &mut State) -> ValueKind {
-    let lhs = visit_node(&node.children[0], state);
-    let rhs = visit_node(&node.children[1], state);
+fn assign(symbol: Symbol, value: ValueKind, target: &Node, state: &mut State) -> EvalResult {
+    if state.observers.is_empty() {
+        state.store(symbol, value.clone());
+    } else {
+        let old = state.slot(symbol).cloned();
+        state.store(symbol, value.clone());
+        state.notify(|o, state| o.variable_write(state.symbols.resolve(symbol), old.as_ref(), &value, target.span));
+    }
 
+    Ok(value)
+}
+
+fn visit_binop_node(node: &Node, state: &mut State) -> EvalResult {
     if let TokenKind::Assign = node.entry {
-        return do_assign_node(&lhs, &rhs, state)
+        return visit_assign_node(node, state)
     }
 
-    do_number_node(&lhs, &rhs, &node.entry, state)
+    let lhs = visit_node(&node.children[0], state)?;
+
+    // The left value waits on the stack while the right side is evaluated
+    state.push_stack(lhs).map_err(|e| locate(e, node))?;
+    let rhs = visit_node(&node.children[1], state);
+    let lhs = state.pop_stack();
+    let rhs = rhs?;
+
+    do_operation(&lhs, &rhs, &node.entry, state).map_err(|e| locate(e, node))
 }
 
-fn visit_unaryop_node(node: &Node, state: &mut State) -> ValueKind {
-    let n = visit_node(node, state);
+fn visit_call_node(node: &Node, state: &mut State) -> EvalResult {
+    let name = match &node.children[0].entry {
+        TokenKind::Identifier(name) => name,
+        other => return Err(locate(RuntimeErrorKind::UnexpectedNode(other.clone()), node))
+    };
+
+    // The arguments are gathered on the stack, which is restored even if
+    // one of them fails
+    let base = state.size_stack();
+    let pushed = push_args(node, state);
+    let args = state.stack.split_off(base);
+    pushed?;
 
+    call(name, &args, node, state)
+}
+
+fn call(name: &str, args: &[ValueKind], node: &Node, state: &mut State) -> EvalResult {
+    state.notify(|o, _| o.call(name, args, node.span));
+    let value = state.call_function(name, args).map_err(|e| locate(e, node))?;
+    state.notify(|o, _| o.call_return(name, &value));
+
+    Ok(value)
+}
+
+fn push_args(node: &Node, state: &mut State) -> Result<(), RuntimeError> {
+    for child in &node.children[1..] {
+        let arg = visit_node(child, state)?;
+        state.push_stack(arg).map_err(|e| locate(e, node))?;
+    }
+
+    Ok(())
+}
+
+fn visit_unaryop_node(node: &Node, state: &mut State) -> EvalResult {
+    let n = visit_node(&node.children[0], state)?;
+    do_unary(n, node, state)
+}
+
+fn do_unary(n: ValueKind, node: &Node, state: &State) -> EvalResult {
     if let TokenKind::Minus = node.entry {
-        do_number_node(&n, &ValueKind::Integer(-1), &TokenKind::Asterisk, state)
+        do_operation(&n, &ValueKind::Integer(-1), &TokenKind::Asterisk, state).map_err(|e| locate(e, node))
     } else {
-        n
+        Ok(n)
     }
 }
 
-pub fn interpret(src: &str, main_state: &mut State) -> i32 {
-    let tree = parser::parse(src).expect("AST(Abstract Syntax Tree) error");
+// Time spent in each phase of interpreting a piece of source
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timings {
+    pub lex: Duration,
+    pub parse: Duration,
+    pub eval: Duration
+}
 
-    //println!("{:#?}", tree);
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.lex + self.parse + self.eval
+    }
+}
+
+impl AddAssign for Timings {
+    fn add_assign(&mut self, other: Timings) {
+        self.lex += other.lex;
+        self.parse += other.parse;
+        self.eval += other.eval;
+    }
+}
+
+// Returns the value the last statement evaluated to
+pub fn interpret(src: &str, main_state: &mut State) -> Result<ValueKind, Error> {
+    interpret_timed(src, main_state).map(|(value, _)| value)
+}
+
+pub fn interpret_timed(src: &str, main_state: &mut State) -> Result<(ValueKind, Timings), Error> {
+    let result = run(src, main_state);
+
+    if let Err(e) = &result {
+        main_state.notify(|o, _| o.error(e));
+    }
+
+    result
+}
+
+// What a run changes in the state while it evaluates, put back once it
+// ends, however it ends. A panic caught by the host would otherwise leave
+// frames and operands behind, and a run inside another one, like the
+// debugger's set, would start the outer run's limits over
+struct RunGuard<'a> {
+    state: &'a mut State,
+    depth: usize,
+    stack: usize,
+    ops: u64,
+    started: Instant
+}
+
+impl<'a> RunGuard<'a> {
+    fn new(state: &'a mut State) -> RunGuard<'a> {
+        let (depth, stack, ops, started) = (state.depth, state.size_stack(), state.ops, state.started);
+        RunGuard { state, depth, stack, ops, started }
+    }
+}
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.state.depth = self.depth;
+        self.state.stack.truncate(self.stack);
+        self.state.ops = self.ops;
+        self.state.started = self.started;
+    }
+}
+
+impl std::ops::Deref for RunGuard<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        self.state
+    }
+}
+
+impl std::ops::DerefMut for RunGuard<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        self.state
+    }
+}
+
+fn run(src: &str, main_state: &mut State) -> Result<(ValueKind, Timings), Error> {
+    let mut guard = RunGuard::new(main_state);
+    let main_state: &mut State = &mut guard;
+    let mut timings = Timings::default();
+
+    let start = Instant::now();
+    let tokens = parser::lex(src)?;
+    timings.lex = start.elapsed();
+
+    let start = Instant::now();
+    let (mut statements, errors) = parser::parse_program_limited(&tokens, main_state.max_depth);
+
+    if !errors.is_empty() {
+        return Err(Error::Syntax(errors));
+    }
+
+    // Hooks get to see the tree as it was written. The passes are taken out
+    // while they run, so they can look at the state
+    if main_state.hook.is_none() {
+        let mut passes = std::mem::take(&mut main_state.passes);
+        passes.run(&mut statements, main_state);
+        main_state.passes = passes;
+    }
+    timings.parse = start.elapsed();
+
+    //println!("{:#?}", statements);
+
+    resolve::resolve(&mut statements, main_state)?;
+
+    let start = Instant::now();
+    main_state.ops = 0;
+    main_state.started = start;
+
+    // Hooks are called around nodes as the tree is walked, so they always
+    // get the tree
+    let eval = match main_state.eval_mode {
+        EvalMode::Stack if main_state.hook.is_none() => machine::eval,
+        _ => visit_node
+    };
 
-    main_state.variables.insert("NULL".to_string(), ValueKind::Integer(0));
+    main_state.failed_stack = None;
 
-    visit_node(&tree, main_state);
+    let mut value = ValueKind::None;
+    for statement in &statements {
+        main_state.notify(|o, _| o.statement_start(statement));
+        value = eval(statement, main_state)?;
+        main_state.notify(|o, _| o.statement_end(statement, &value));
+    }
+    timings.eval = start.elapsed();
+
+    Ok((value, timings))
+}
+
+// The tree of every statement in the source, or every syntax error in it
+pub fn parse(src: &str) -> Result<Vec<Node>, Error> {
+    parse_limited(src, DEFAULT_MAX_DEPTH)
+}
+
+// Like parse, with expressions nesting up to `max_depth` levels
+pub fn parse_limited(src: &str, max_depth: usize) -> Result<Vec<Node>, Error> {
+    let tokens = parser::lex(src)?;
+    let (statements, errors) = parser::parse_program_limited(&tokens, max_depth);
+
+    if errors.is_empty() {
+        Ok(statements)
+    } else {
+        Err(Error::Syntax(errors))
+    }
+}
+
+// Lexes and parses the source without running it, reporting every syntax
+// error found, or the warnings for code that parsed fine
+pub fn check(src: &str) -> Result<Vec<Warning>, Error> {
+    check_limited(src, DEFAULT_MAX_DEPTH)
+}
 
-    0
+// Like check, with expressions nesting up to `max_depth` levels
+pub fn check_limited(src: &str, max_depth: usize) -> Result<Vec<Warning>, Error> {
+    parse_limited(src, max_depth).map(|statements| analysis::analyze(&statements))
 }
\ No newline at end of file
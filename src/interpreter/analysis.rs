@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+
+use super::parser::{Node, NodeVisitor, Span, TokenKind};
+use super::PRELUDE_NAMESPACE;
+
+// Something suspicious that doesn't stop the program from running
+#[derive(Debug, Clone)]
+pub struct Warning {
+    // A stable name for the kind of warning, for tools that read diagnostics
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Where a variable was last assigned, and whether that value has been read
+struct Assignment {
+    span: Span,
+    read: bool
+}
+
+#[derive(Default)]
+struct Analyzer {
+    assignments: HashMap<String, Assignment>,
+    // Variables read at least once, to tell unused ones from dead stores
+    ever_read: HashSet<String>,
+    // First assignment of every variable, in order
+    declarations: Vec<(String, Span)>,
+    // Assignments whose value was never read
+    dead_stores: Vec<(String, Span)>,
+    warnings: Vec<Warning>
+}
+
+impl NodeVisitor for Analyzer {
+    // The right side of an assignment is evaluated before the variable is
+    // written
+    fn visit_assign(&mut self, _node: &Node, target: &Node, value: &Node) {
+        self.visit(value);
+
+        if let TokenKind::Identifier(name) = &target.entry {
+            // Refused at runtime in every mode, only a prelude may do this
+            if name.starts_with(PRELUDE_NAMESPACE) {
+                self.warnings.push(Warning {
+                    code: "assigned-constant",
+                    message: format!("`{}` is under {}, which belongs to the prelude", name, PRELUDE_NAMESPACE),
+                    span: target.span
+                });
+            }
+
+            self.write(name, target.span);
+        }
+    }
+
+    fn visit_identifier(&mut self, _node: &Node, name: &str) {
+        self.read(name);
+    }
+}
+
+impl Analyzer {
+    fn read(&mut self, name: &str) {
+        if let Some(assignment) = self.assignments.get_mut(name) {
+            assignment.read = true;
+        }
+        self.ever_read.insert(name.to_string());
+    }
+
+    fn write(&mut self, name: &str, span: Span) {
+        let previous = self.assignments.insert(name.to_string(), Assignment { span, read: false });
+
+        match previous {
+            Some(previous) if !previous.read => self.dead_stores.push((name.to_string(), previous.span)),
+            Some(_) => {},
+            None => self.declarations.push((name.to_string(), span))
+        }
+    }
+
+    fn finish(mut self) -> Vec<Warning> {
+        // Names starting with an underscore are meant to be unused
+        for (name, span) in &self.declarations {
+            if !self.ever_read.contains(name) && !name.starts_with('_') {
+                self.warnings.push(Warning {
+                    code: "unused-variable",
+                    message: format!("Variable `{}` is never used", name),
+                    span: *span
+                });
+            }
+        }
+
+        // Unused variables already got a warning of their own
+        for (name, span) in &self.dead_stores {
+            if self.ever_read.contains(name) {
+                self.warnings.push(Warning {
+                    code: "dead-store",
+                    message: format!("Value assigned to `{}` is overwritten before being read", name),
+                    span: *span
+                });
+            }
+        }
+
+        self.warnings.sort_by_key(|w| (w.span.line, w.span.col));
+        self.warnings
+    }
+}
+
+// Assignments and calls are the only things with an effect, as host
+// functions can do anything
+pub fn has_effect(node: &Node) -> bool {
+    matches!(node.entry, TokenKind::Assign | TokenKind::Lparen) || node.children.iter().any(has_effect)
+}
+
+// Looks for unused variables, values that are overwritten before anybody
+// reads them, statements whose result is thrown away, and assignments to
+// prelude constants
+pub fn analyze(statements: &[Node]) -> Vec<Warning> {
+    let mut analyzer = Analyzer::default();
+
+    for statement in statements {
+        if !has_effect(statement) {
+            analyzer.warnings.push(Warning {
+                code: "no-effect",
+                message: String::from("Statement has no effect"),
+                span: statement.span
+            });
+        }
+
+        analyzer.visit(statement);
+    }
+
+    analyzer.finish()
+}
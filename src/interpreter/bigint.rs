@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// Each limb holds nine decimal digits, so printing doesn't need division
+const BASE: u64 = 1_000_000_000;
+
+// An integer of any size, for scripts that outgrow i64. The magnitude is
+// kept in limbs, least significant first, without trailing zero limbs, so
+// zero has none and is never negative
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>
+}
+
+impl BigInt {
+    fn new(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        let negative = negative && !limbs.is_empty();
+        BigInt { negative, limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // The value as an i64, if it fits
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut magnitude: i128 = 0;
+
+        for &limb in self.limbs.iter().rev() {
+            magnitude = magnitude * BASE as i128 + limb as i128;
+            if magnitude > i64::MAX as i128 + 1 {
+                return None;
+            }
+        }
+
+        i64::try_from(if self.negative { -magnitude } else { magnitude }).ok()
+    }
+
+    // The closest decimal, which is infinite for huge values
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.limbs.iter().rev().fold(0.0, |n, &limb| n * BASE as f64 + limb as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut result = BigInt::from(1i64);
+        let mut base = self.clone();
+
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = &result * &base;
+            }
+            base = &base * &base;
+            exponent /= 2;
+        }
+
+        result
+    }
+
+    // The greatest common divisor of the magnitudes, which is never negative
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (BigInt::new(false, self.limbs.clone()), BigInt::new(false, other.limbs.clone()));
+
+        while let Some((_, remainder)) = a.div_rem(&b) {
+            (a, b) = (b, remainder);
+        }
+
+        a
+    }
+
+    // The quotient rounded towards zero and the remainder, which has the
+    // sign of `self` like for i64. None when dividing by zero
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_limbs(&self.limbs, &other.limbs);
+        Some((BigInt::new(self.negative != other.negative, quotient), BigInt::new(self.negative, remainder)))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_limbs(&self.limbs, &other.limbs));
+        }
+
+        // Different signs, so the smaller magnitude is taken from the larger
+        match compare_limbs(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::new(other.negative, sub_limbs(&other.limbs, &self.limbs)),
+            _ => BigInt::new(self.negative, sub_limbs(&self.limbs, &other.limbs))
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(self.negative != other.negative, mul_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+fn compare_limbs(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+
+    for i in 0..a.len().max(b.len()) {
+        let n = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push((n % BASE) as u32);
+        carry = n / BASE;
+    }
+
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+// `a` has to be at least as large as `b`
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+
+    for (i, &limb) in a.iter().enumerate() {
+        let mut n = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+
+        if n < 0 {
+            n += BASE as i64;
+            borrow = 1;
+        }
+        difference.push(n as u32);
+    }
+
+    while difference.last() == Some(&0) {
+        difference.pop();
+    }
+    difference
+}
+
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+
+    let mut product = vec![0u64; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+
+        for (j, &y) in b.iter().enumerate() {
+            let n = product[i + j] + x as u64 * y as u64 + carry;
+            product[i + j] = n % BASE;
+            carry = n / BASE;
+        }
+
+        product[i + b.len()] += carry;
+    }
+
+    let mut product: Vec<u32> = product.into_iter().map(|n| n as u32).collect();
+    while product.last() == Some(&0) {
+        product.pop();
+    }
+    product
+}
+
+// Long division, one limb of the quotient at a time. Each limb is found by
+// bisection, which is slow for huge divisors but simple
+fn div_rem_limbs(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+
+    for i in (0..a.len()).rev() {
+        remainder.insert(0, a[i]);
+        while remainder.last() == Some(&0) {
+            remainder.pop();
+        }
+
+        let (mut low, mut high) = (0, BASE as u32 - 1);
+        while low < high {
+            let middle = low + (high - low).div_ceil(2);
+
+            if compare_limbs(&mul_limbs(b, &[middle]), &remainder) == Ordering::Greater {
+                high = middle - 1;
+            } else {
+                low = middle;
+            }
+        }
+
+        quotient[i] = low;
+        remainder = sub_limbs(&remainder, &mul_limbs(b, &[low]));
+    }
+
+    (quotient, remainder)
+}
+
+impl From<i64> for BigInt {
+    fn from(other: i64) -> BigInt {
+        BigInt::from(other as i128)
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(other: i128) -> BigInt {
+        let mut magnitude = other.unsigned_abs();
+        let mut limbs = Vec::new();
+
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE as u128) as u32);
+            magnitude /= BASE as u128;
+        }
+
+        BigInt::new(other < 0, limbs)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_limbs(&self.limbs, &other.limbs),
+            (true, true) => compare_limbs(&other.limbs, &self.limbs)
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Decimal digits with an optional leading minus
+impl std::str::FromStr for BigInt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BigInt, String> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s)
+        };
+
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("'{}' is not an integer", s));
+        }
+
+        // Nine digits at a time, from the end
+        let limbs = digits.as_bytes()
+            .rchunks(9)
+            .map(|chunk| chunk.iter().fold(0, |n, b| n * 10 + (b - b'0') as u32))
+            .collect();
+
+        Ok(BigInt::new(negative, limbs))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some((most, rest)) = self.limbs.split_last() else {
+            return write!(f, "0");
+        };
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        write!(f, "{}", most)?;
+        for limb in rest.iter().rev() {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+// Values are printed with Debug, where the limbs would be unreadable
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigInt;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn carries_and_borrows_across_limbs() {
+        assert_eq!(&big("999999999") + &big("1"), big("1000000000"));
+        assert_eq!(&big("999999999999999999") + &big("1"), big("1000000000000000000"));
+        assert_eq!(&big("1000000000") - &big("1"), big("999999999"));
+        assert_eq!(&big("1000000000000000000") - &big("1"), big("999999999999999999"));
+        assert_eq!(&big("999999999") * &big("999999999"), big("999999998000000001"));
+        assert_eq!(&big("1") - &big("1000000000"), big("-999999999"));
+    }
+
+    #[test]
+    fn divides_by_several_limbs() {
+        let a = big("123456789012345678901234567890");
+        let b = big("987654321098765432");
+        let (quotient, remainder) = a.div_rem(&b).unwrap();
+
+        assert_eq!(quotient, big("124999998860"));
+        assert_eq!(remainder, big("925925953827160370"));
+        assert_eq!(&(&quotient * &b) + &remainder, a);
+        assert!(big("1").div_rem(&BigInt::default()).is_none());
+    }
+
+    #[test]
+    fn quotient_rounds_towards_zero_and_remainder_follows_the_dividend() {
+        let divide = |a: &str, b: &str| {
+            let (quotient, remainder) = big(a).div_rem(&big(b)).unwrap();
+            (quotient.to_string(), remainder.to_string())
+        };
+
+        assert_eq!(divide("7", "2"), ("3".to_string(), "1".to_string()));
+        assert_eq!(divide("-7", "2"), ("-3".to_string(), "-1".to_string()));
+        assert_eq!(divide("7", "-2"), ("-3".to_string(), "1".to_string()));
+        assert_eq!(divide("-7", "-2"), ("3".to_string(), "-1".to_string()));
+        assert_eq!(divide("-1", "1000000000000"), ("0".to_string(), "-1".to_string()));
+    }
+
+    #[test]
+    fn fits_into_i64_at_the_edges() {
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(BigInt::from(i64::MAX).to_i64(), Some(i64::MAX));
+        assert_eq!((&BigInt::from(i64::MAX) + &BigInt::from(1i64)).to_i64(), None);
+        assert_eq!((&BigInt::from(i64::MIN) - &BigInt::from(1i64)).to_i64(), None);
+    }
+
+    #[test]
+    fn parses_and_prints_the_same_digits() {
+        for s in ["0", "7", "-7", "1000000000", "-1000000001", "123456789012345678901234567890"] {
+            assert_eq!(big(s).to_string(), s);
+        }
+
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000000000000042").to_string(), "42");
+        assert!("".parse::<BigInt>().is_err());
+        assert!("-".parse::<BigInt>().is_err());
+        assert!("12a".parse::<BigInt>().is_err());
+    }
+}
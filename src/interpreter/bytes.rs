@@ -0,0 +1,224 @@
+// Builtins for binary data: reading and writing files as bytes, taking
+// bytes apart, converting between bytes and strings in an encoding, and
+// base64 and hex. Indexing is a function, since the language has no
+// syntax for it
+use std::fs;
+
+use super::{Capabilities, Error, State, ValueKind};
+
+pub fn arity(args: &[ValueKind], counts: &[usize]) -> Result<(), Error> {
+    if counts.contains(&args.len()) {
+        Ok(())
+    } else {
+        let expected: Vec<String> = counts.iter().map(usize::to_string).collect();
+        Err(Error::Io(format!("Expected {} arguments, but got {}", expected.join(" or "), args.len())))
+    }
+}
+
+pub fn bytes(value: &ValueKind) -> Result<&[u8], Error> {
+    match value {
+        ValueKind::Bytes(bytes) => Ok(bytes),
+        other => Err(Error::Io(format!("Expected bytes, but got {}", other.type_name())))
+    }
+}
+
+pub fn string(value: &ValueKind) -> Result<&str, Error> {
+    match value {
+        ValueKind::Str(s) => Ok(s),
+        other => Err(Error::Io(format!("Expected a string, but got {}", other.type_name())))
+    }
+}
+
+fn out_of_range(value: &ValueKind, len: usize) -> Error {
+    Error::Io(format!("{} is out of range for a length of {}", value, len))
+}
+
+// An offset into something `len` long, which may be `len` itself for the end
+// of a slice. Negative offsets count from the end, so -1 is the last element
+pub fn offset(value: &ValueKind, len: usize) -> Result<usize, Error> {
+    let ValueKind::Integer(n) = value else {
+        return Err(Error::Io(format!("Expected an integer, but got {}", value.type_name())));
+    };
+
+    let i = if *n < 0 { n.checked_add(len as i64) } else { Some(*n) };
+    match i {
+        Some(i) if (0..=len as i64).contains(&i) => Ok(i as usize),
+        _ => Err(out_of_range(value, len))
+    }
+}
+
+// Like `offset` for one element, which has to come before the end
+pub fn index(value: &ValueKind, len: usize) -> Result<usize, Error> {
+    match offset(value, len)? {
+        i if i < len => Ok(i),
+        _ => Err(out_of_range(value, len))
+    }
+}
+
+// Strings are encoded as UTF-8
+fn contents(value: &ValueKind) -> Result<&[u8], Error> {
+    match value {
+        ValueKind::Str(s) => Ok(s.as_bytes()),
+        other => bytes(other)
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// The standard alphabet, padded with =
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// Padding is optional, and whitespace is skipped so wrapped text decodes
+fn base64_decode(text: &str) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::Io(String::from("The string isn't valid base64"));
+
+    let digits: Vec<u8> = text.trim_end_matches(|c: char| c == '=' || c.is_whitespace())
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .map(|b| BASE64.iter().position(|&d| d == b).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+
+    if digits.len() % 4 == 1 {
+        return Err(invalid());
+    }
+
+    let mut decoded = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &d)| n | (d as u32) << (18 - 6 * i));
+        decoded.extend(n.to_be_bytes()[1..chunk.len()].iter());
+    }
+    Ok(decoded)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Either case
+fn hex_decode(text: &str) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::Io(String::from("The string isn't valid hex"));
+
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Utf8,
+    Latin1,
+    Ascii
+}
+
+// UTF-8 unless another one is named
+fn encoding(args: &[ValueKind]) -> Result<Encoding, Error> {
+    let Some(name) = args.get(1) else {
+        return Ok(Encoding::Utf8);
+    };
+
+    match string(name)?.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => Ok(Encoding::Utf8),
+        "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+        "ascii" => Ok(Encoding::Ascii),
+        other => Err(Error::Io(format!("Unknown encoding '{}', expected utf-8, latin-1 or ascii", other)))
+    }
+}
+
+fn encode(text: &str, encoding: Encoding) -> Result<Vec<u8>, Error> {
+    let limit = match encoding {
+        Encoding::Utf8 => return Ok(text.as_bytes().to_vec()),
+        Encoding::Latin1 => 0xff,
+        Encoding::Ascii => 0x7f
+    };
+
+    text.chars()
+        .map(|c| u8::try_from(c as u32).ok().filter(|&b| b <= limit))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| Error::Io(String::from("The string has characters the encoding can't hold")))
+}
+
+fn decode(bytes: &[u8], encoding: Encoding) -> Result<String, Error> {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|e| Error::Io(format!("Invalid UTF-8: {}", e))),
+        Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+        Encoding::Ascii if bytes.is_ascii() => Ok(bytes.iter().map(|&b| b as char).collect()),
+        Encoding::Ascii => Err(Error::Io(String::from("The bytes aren't ASCII")))
+    }
+}
+
+pub fn register(state: &mut State) {
+    state.register_fn_requiring("read_bytes", Capabilities::FILESYSTEM, |args| {
+        arity(args, &[1])?;
+        let path = string(&args[0])?;
+        let contents = fs::read(path).map_err(|e| Error::Io(format!("Can't read {}: {}", path, e)))?;
+        Ok(ValueKind::from(contents))
+    });
+
+    state.register_fn_requiring("write_bytes", Capabilities::FILESYSTEM, |args| {
+        arity(args, &[2])?;
+        let path = string(&args[0])?;
+        fs::write(path, bytes(&args[1])?).map_err(|e| Error::Io(format!("Can't write {}: {}", path, e)))?;
+        Ok(ValueKind::None)
+    });
+
+    // to_bytes(text) or to_bytes(text, encoding)
+    state.register_fn("to_bytes", |args| {
+        arity(args, &[1, 2])?;
+        Ok(ValueKind::from(encode(string(&args[0])?, encoding(args)?)?))
+    });
+
+    // from_bytes(bytes) or from_bytes(bytes, encoding)
+    state.register_fn("from_bytes", |args| {
+        arity(args, &[1, 2])?;
+        Ok(ValueKind::from(decode(bytes(&args[0])?, encoding(args)?)?))
+    });
+
+    // The encoders take strings or bytes, and the decoders return bytes
+    state.register_fn("base64_encode", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(base64_encode(contents(&args[0])?)))
+    });
+
+    state.register_fn("base64_decode", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(base64_decode(string(&args[0])?)?))
+    });
+
+    state.register_fn("hex_encode", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(hex_encode(contents(&args[0])?)))
+    });
+
+    state.register_fn("hex_decode", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(hex_decode(string(&args[0])?)?))
+    });
+
+    // The byte at an offset, as an integer from 0 to 255
+    state.register_fn("byte_at", |args| {
+        arity(args, &[2])?;
+        let bytes = bytes(&args[0])?;
+        Ok(ValueKind::Integer(bytes[index(&args[1], bytes.len())?] as i64))
+    });
+}
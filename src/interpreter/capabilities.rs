@@ -0,0 +1,70 @@
+use std::fmt;
+use std::ops::BitOr;
+
+// Side effects a script may cause through builtins. Builtins declare what
+// they need when registered, and calls are refused when the state doesn't
+// grant all of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    pub const NONE: Capabilities = Capabilities(0);
+    pub const FILESYSTEM: Capabilities = Capabilities(1);
+    pub const NETWORK: Capabilities = Capabilities(1 << 1);
+    pub const SUBPROCESS: Capabilities = Capabilities(1 << 2);
+    pub const ENV: Capabilities = Capabilities(1 << 3);
+    pub const ALL: Capabilities = Capabilities(0b1111);
+
+    const NAMES: [(Capabilities, &'static str); 4] = [
+        (Capabilities::FILESYSTEM, "filesystem"),
+        (Capabilities::NETWORK, "network"),
+        (Capabilities::SUBPROCESS, "subprocess"),
+        (Capabilities::ENV, "env")
+    ];
+
+    // Bits outside of the known capabilities are dropped
+    pub fn from_bits(bits: u8) -> Capabilities {
+        Capabilities(bits & Capabilities::ALL.0)
+    }
+
+    pub fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Capabilities) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Capabilities) {
+        self.0 &= !other.0;
+    }
+
+    // The capabilities of `self` that `other` lacks
+    pub fn missing_from(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 & !other.0)
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Capabilities;
+
+    fn bitor(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 | other.0)
+    }
+}
+
+// Written as a comma separated list like "filesystem, env"
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = Capabilities::NAMES.iter()
+            .filter(|(capability, _)| self.contains(*capability))
+            .map(|(_, name)| *name)
+            .collect();
+
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(", "))
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Equality, ordering and hashing of values, shared by the `==`, `!=`, `<`
+// and `>` operators and by embedders comparing or hashing values.
+//
+// Numbers of every kind compare by their exact value, so 1, 1.0, 1n, 1.0d
+// and 2 / 2 are all equal, 0.1 + 0.2 isn't 0.3d, and 2^53 + 1 is above the
+// decimal 2^53. NaN equals nothing and has no order, like in f64. Strings
+// compare by their text, bytes byte by byte, and false is below true. Values of different
+// kinds otherwise are never equal and have no order, and none only equals
+// none. Equal values hash the same
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use super::{BigInt, ValueKind};
+
+// Integers up to this size are exact as f64
+const EXACT_IN_F64: u64 = 1 << 53;
+
+// A number as a fraction with a positive denominator, or None for anything
+// that isn't a finite number
+fn fraction(value: &ValueKind) -> Option<(BigInt, BigInt)> {
+    let one = || BigInt::from(1i64);
+
+    match value {
+        ValueKind::Integer(n) => Some((BigInt::from(*n), one())),
+        ValueKind::BigInt(n) => Some(((**n).clone(), one())),
+        ValueKind::Rational(n, d) => Some((BigInt::from(*n), BigInt::from(*d))),
+        ValueKind::Fixed(n) => {
+            let (units, scale) = n.parts();
+            Some((BigInt::from(units), BigInt::from(10i64).pow(scale)))
+        },
+        ValueKind::Decimal(n) if n.is_finite() => Some(decimal_fraction(*n)),
+        _ => None
+    }
+}
+
+// Every finite f64 is a whole number times a power of two
+fn decimal_fraction(n: f64) -> (BigInt, BigInt) {
+    let bits = n.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+
+    // Subnormal numbers have no implicit leading bit
+    let (mantissa, exponent) = if exponent == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | 1 << 52, exponent - 1075)
+    };
+
+    let mantissa = BigInt::from(if n < 0.0 { -(mantissa as i64) } else { mantissa as i64 });
+
+    if exponent >= 0 {
+        (&mantissa * &BigInt::from(2i64).pow(exponent as u32), BigInt::from(1i64))
+    } else {
+        (mantissa, BigInt::from(2i64).pow(-exponent as u32))
+    }
+}
+
+pub fn is_number(value: &ValueKind) -> bool {
+    matches!(
+        value,
+        ValueKind::Integer(_) | ValueKind::Decimal(_) | ValueKind::BigInt(_) | ValueKind::Fixed(_) | ValueKind::Rational(..)
+    )
+}
+
+fn compare_numbers(a: &ValueKind, b: &ValueKind) -> Option<Ordering> {
+    match (a, b) {
+        (ValueKind::Integer(a), ValueKind::Integer(b)) => Some(a.cmp(b)),
+        (ValueKind::Decimal(a), ValueKind::Decimal(b)) => a.partial_cmp(b),
+        (ValueKind::Integer(a), ValueKind::Decimal(b)) if a.unsigned_abs() <= EXACT_IN_F64 => (*a as f64).partial_cmp(b),
+        (ValueKind::Decimal(a), ValueKind::Integer(b)) if b.unsigned_abs() <= EXACT_IN_F64 => a.partial_cmp(&(*b as f64)),
+        // Infinity is beyond every exact number, and NaN isn't comparable
+        (ValueKind::Decimal(n), _) | (_, ValueKind::Decimal(n)) if !n.is_finite() => {
+            let a = f64::try_from(a.clone()).ok()?;
+            let b = f64::try_from(b.clone()).ok()?;
+            a.partial_cmp(&b)
+        },
+        _ => {
+            let (an, ad) = fraction(a)?;
+            let (bn, bd) = fraction(b)?;
+            Some((&an * &bd).cmp(&(&bn * &ad)))
+        }
+    }
+}
+
+impl PartialEq for ValueKind {
+    fn eq(&self, other: &ValueKind) -> bool {
+        match (self, other) {
+            (ValueKind::Str(a), ValueKind::Str(b)) => a == b,
+            (ValueKind::Bytes(a), ValueKind::Bytes(b)) => a == b,
+            (ValueKind::Boolean(a), ValueKind::Boolean(b)) => a == b,
+            (ValueKind::Identifier(a), ValueKind::Identifier(b)) => a == b,
+            (ValueKind::None, ValueKind::None) => true,
+            (a, b) => compare_numbers(a, b) == Some(Ordering::Equal)
+        }
+    }
+}
+
+impl PartialOrd for ValueKind {
+    fn partial_cmp(&self, other: &ValueKind) -> Option<Ordering> {
+        match (self, other) {
+            (ValueKind::Str(a), ValueKind::Str(b)) => Some(a.cmp(b)),
+            (ValueKind::Bytes(a), ValueKind::Bytes(b)) => Some(a.cmp(b)),
+            (ValueKind::Boolean(a), ValueKind::Boolean(b)) => Some(a.cmp(b)),
+            (ValueKind::None, ValueKind::None) => Some(Ordering::Equal),
+            (a, b) => compare_numbers(a, b)
+        }
+    }
+}
+
+impl Hash for ValueKind {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            ValueKind::Str(s) => (0u8, s).hash(state),
+            ValueKind::Boolean(b) => (1u8, b).hash(state),
+            ValueKind::Identifier(symbol) => (2u8, symbol).hash(state),
+            ValueKind::None => 3u8.hash(state),
+            ValueKind::Bytes(bytes) => (7u8, bytes).hash(state),
+            // Whole numbers that fit are hashed as integers, and the rest
+            // as the fraction in lowest terms
+            ValueKind::Integer(n) => (4u8, n).hash(state),
+            ValueKind::Decimal(n) if n.fract() == 0.0 && n.abs() <= EXACT_IN_F64 as f64 => (4u8, *n as i64).hash(state),
+            number => match fraction(number) {
+                Some((n, d)) => {
+                    let divisor = n.gcd(&d);
+                    let (n, d) = (n.div_rem(&divisor).unwrap_or_default().0, d.div_rem(&divisor).unwrap_or_default().0);
+
+                    match n.to_i64() {
+                        Some(n) if d == BigInt::from(1i64) => (4u8, n).hash(state),
+                        _ => (5u8, n, d).hash(state)
+                    }
+                },
+                // Infinities and NaN
+                None => (6u8, f64::try_from(number.clone()).unwrap_or_default().to_bits()).hash(state)
+            }
+        }
+    }
+}
+
+// Whether the values can be put in order, so `<` and `>` can report an
+// error for a string next to a number
+pub fn comparable(a: &ValueKind, b: &ValueKind) -> bool {
+    matches!(
+        (a, b),
+        (ValueKind::Str(_), ValueKind::Str(_))
+            | (ValueKind::Bytes(_), ValueKind::Bytes(_))
+            | (ValueKind::Boolean(_), ValueKind::Boolean(_))
+            | (ValueKind::None, ValueKind::None)
+    ) || (is_number(a) && is_number(b))
+}
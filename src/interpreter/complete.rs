@@ -0,0 +1,64 @@
+use std::ops::Range;
+
+use super::State;
+
+// Words with a meaning of their own that can be completed
+pub const KEYWORDS: &[&str] = &["true", "false", "none"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Keyword
+}
+
+// A word that fits where the cursor is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    // The bytes of the line the label replaces, the part of the word before
+    // the cursor
+    pub replace: Range<usize>
+}
+
+fn is_word_char(c: char) -> bool {
+    c == '_' || c == '.' || c.is_alphanumeric()
+}
+
+// Every variable, builtin and keyword starting with the word before `cursor`,
+// a byte offset into `line`, sorted by kind and then name. Nothing is
+// offered inside a string or right after a number
+pub fn complete(state: &State, line: &str, cursor: usize) -> Vec<Completion> {
+    let cursor = cursor.min(line.len());
+    let before = match line.get(..cursor) {
+        Some(before) => before,
+        None => return Vec::new()
+    };
+
+    if before.matches('"').count() % 2 == 1 {
+        return Vec::new();
+    }
+
+    let start = before.char_indices().rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(cursor, |(i, _)| i);
+    let prefix = &before[start..];
+
+    if prefix.starts_with(|c: char| c.is_ascii_digit()) {
+        return Vec::new();
+    }
+
+    let variables = state.vars().map(|(name, _)| (name, CompletionKind::Variable));
+    let functions = state.function_names().map(|name| (name, CompletionKind::Function));
+    let keywords = KEYWORDS.iter().map(|name| (*name, CompletionKind::Keyword));
+
+    let mut completions: Vec<Completion> = variables.chain(functions).chain(keywords)
+        .filter(|(name, _)| name.starts_with(prefix))
+        .map(|(name, kind)| Completion { label: name.to_string(), kind, replace: start..cursor })
+        .collect();
+
+    completions.sort_by(|a, b| (a.kind, &a.label).cmp(&(b.kind, &b.label)));
+    completions
+}
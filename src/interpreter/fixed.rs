@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// How many decimal places a quotient is worked out to before rounding
+pub const DIVISION_PLACES: u32 = 18;
+
+// A decimal number stored exactly, as `units` divided by 10 to the power of
+// `scale`, so 1.10 is 110 with a scale of 2. The scale is kept, so the
+// result prints as many places as its operands had
+#[derive(Clone, Copy, Default)]
+pub struct Fixed {
+    units: i128,
+    scale: u32
+}
+
+fn power_of_ten(exponent: u32) -> Option<i128> {
+    10i128.checked_pow(exponent)
+}
+
+impl Fixed {
+    pub fn new(units: i128, scale: u32) -> Fixed {
+        Fixed { units, scale }
+    }
+
+    // The units and the scale, so the value is units / 10^scale
+    pub fn parts(self) -> (i128, u32) {
+        (self.units, self.scale)
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.units == 0
+    }
+
+    // The units at a larger scale, or None if they don't fit
+    fn units_at(self, scale: u32) -> Option<i128> {
+        self.units.checked_mul(power_of_ten(scale - self.scale)?)
+    }
+
+    // Both operands at the larger of their scales
+    fn align(self, other: Fixed) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Some((self.units_at(scale)?, other.units_at(scale)?, scale))
+    }
+
+    pub fn checked_add(self, other: Fixed) -> Option<Fixed> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Fixed::new(a.checked_add(b)?, scale))
+    }
+
+    pub fn checked_sub(self, other: Fixed) -> Option<Fixed> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Fixed::new(a.checked_sub(b)?, scale))
+    }
+
+    pub fn checked_mul(self, other: Fixed) -> Option<Fixed> {
+        Some(Fixed::new(self.units.checked_mul(other.units)?, self.scale + other.scale))
+    }
+
+    // The quotient rounded to DIVISION_PLACES places, half to even, with
+    // trailing zeros dropped down to the scale of the operands, so
+    // 1.00 / 4 is 0.25. Fewer places are used when that many don't fit,
+    // and None means dividing by zero or not fitting at all
+    pub fn checked_div(self, other: Fixed) -> Option<Fixed> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let keep = self.scale.max(other.scale);
+
+        // a / 10^sa divided by b / 10^sb, at `places` places, is
+        // a * 10^(sb + places) / (b * 10^sa)
+        let (places, quotient) = (keep..=DIVISION_PLACES.max(keep)).rev().find_map(|places| {
+            let numerator = self.units.checked_mul(power_of_ten(other.scale + places)?)?;
+            let denominator = other.units.checked_mul(power_of_ten(self.scale)?)?;
+            Some((places, divide_rounded(numerator, denominator)?))
+        })?;
+
+        let mut result = Fixed::new(quotient, places);
+        while result.scale > keep && result.units % 10 == 0 {
+            result = Fixed::new(result.units / 10, result.scale - 1);
+        }
+        Some(result)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+// Integer division rounding half to even, like banks do
+fn divide_rounded(numerator: i128, denominator: i128) -> Option<i128> {
+    let quotient = numerator.checked_div(denominator)?;
+    let remainder = (numerator % denominator).unsigned_abs();
+    let twice = remainder * 2;
+    let divisor = denominator.unsigned_abs();
+
+    let round_away = twice > divisor || (twice == divisor && quotient % 2 != 0);
+    if !round_away {
+        Some(quotient)
+    } else if (numerator < 0) != (denominator < 0) {
+        quotient.checked_sub(1)
+    } else {
+        quotient.checked_add(1)
+    }
+}
+
+impl From<i64> for Fixed {
+    fn from(other: i64) -> Fixed {
+        Fixed::new(other as i128, 0)
+    }
+}
+
+// Compares the numbers, so 1.10 equals 1.1
+impl Ord for Fixed {
+    fn cmp(&self, other: &Fixed) -> Ordering {
+        match self.align(*other) {
+            Some((a, b, _)) => a.cmp(&b),
+            // Too many digits to line up, where no precision is lost anyway
+            None => self.to_f64().total_cmp(&other.to_f64())
+        }
+    }
+}
+
+impl PartialOrd for Fixed {
+    fn partial_cmp(&self, other: &Fixed) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Fixed {
+    fn eq(&self, other: &Fixed) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Fixed {}
+
+// Digits with an optional leading minus and decimal point, like -12.50
+impl std::str::FromStr for Fixed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Fixed, String> {
+        let invalid = || format!("'{}' is not a decimal number", s);
+
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s)
+        };
+
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let units: i128 = format!("{}{}", whole, fraction).parse().map_err(|_| invalid())?;
+        Ok(Fixed::new(if negative { -units } else { units }, fraction.len() as u32))
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = format!("{:0>width$}", self.units.unsigned_abs(), width = self.scale as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - self.scale as usize);
+
+        if self.units < 0 {
+            write!(f, "-")?;
+        }
+
+        if fraction.is_empty() {
+            write!(f, "{}", whole)
+        } else {
+            write!(f, "{}.{}", whole, fraction)
+        }
+    }
+}
+
+// Values are printed with Debug, where the units would be confusing
+impl fmt::Debug for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{divide_rounded, Fixed};
+
+    fn fixed(s: &str) -> Fixed {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn adds_tenths_exactly() {
+        let sum = fixed("0.1").checked_add(fixed("0.2")).unwrap();
+
+        assert_eq!(sum, fixed("0.3"));
+        assert_eq!(sum.to_string(), "0.3");
+        assert_eq!(fixed("1.10").checked_sub(fixed("0.1")).unwrap().to_string(), "1.00");
+        assert_eq!(fixed("1.5").checked_mul(fixed("1.5")).unwrap().to_string(), "2.25");
+    }
+
+    #[test]
+    fn divides_rounding_half_to_even() {
+        assert_eq!(divide_rounded(5, 2), Some(2));
+        assert_eq!(divide_rounded(7, 2), Some(4));
+        assert_eq!(divide_rounded(-5, 2), Some(-2));
+        assert_eq!(divide_rounded(7, -2), Some(-4));
+        assert_eq!(divide_rounded(8, 3), Some(3));
+
+        let divide = |a: &str, b: &str| fixed(a).checked_div(fixed(b)).unwrap().to_string();
+        assert_eq!(divide("1", "3"), "0.333333333333333333");
+        assert_eq!(divide("2", "3"), "0.666666666666666667");
+        assert_eq!(divide("1.00", "4"), "0.25");
+        assert_eq!(divide("1", "8"), "0.125");
+        assert_eq!(divide("10", "4"), "2.5");
+    }
+
+    #[test]
+    fn overflow_and_division_by_zero_are_none() {
+        let huge = Fixed::new(i128::MAX, 0);
+
+        assert!(huge.checked_add(Fixed::from(1)).is_none());
+        assert!(Fixed::new(i128::MIN, 0).checked_sub(Fixed::from(1)).is_none());
+        assert!(huge.checked_mul(Fixed::from(2)).is_none());
+        // Lining up the scales overflows too
+        assert!(huge.checked_add(fixed("0.1")).is_none());
+        assert!(Fixed::from(1).checked_div(fixed("0.0")).is_none());
+        // Fewer places are used when a quotient doesn't fit at all of them
+        assert_eq!(Fixed::new(i128::MAX / 10, 0).checked_div(Fixed::from(1)).unwrap(), Fixed::new(i128::MAX / 10, 0));
+    }
+
+    #[test]
+    fn negative_values_keep_their_places() {
+        assert_eq!(fixed("-0.05").to_string(), "-0.05");
+        assert_eq!(fixed("-1.10"), fixed("-1.1"));
+        assert!(fixed("-0.5") < fixed("-0.25"));
+        assert_eq!(fixed("-0.1").checked_add(fixed("0.1")).unwrap().to_string(), "0.0");
+        assert_eq!(fixed("-1").checked_div(fixed("3")).unwrap().to_string(), "-0.333333333333333333");
+        assert_eq!(fixed("-1").checked_div(fixed("-8")).unwrap().to_string(), "0.125");
+        assert!("-".parse::<Fixed>().is_err());
+        assert!("--1".parse::<Fixed>().is_err());
+        assert!(".5".parse::<Fixed>().is_err());
+    }
+}
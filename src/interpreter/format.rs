@@ -0,0 +1,134 @@
+// printf-style formatting for the `%` operator, as in "x=%d" % x. Each
+// directive is %[-][width][.precision] followed by one of
+//
+//   d  an integer
+//   f  a number as a decimal, with 6 places unless a precision is given
+//   x  an integer in lowercase hex
+//   s  any value, with strings unquoted
+//
+// and %% is a percent sign. The width pads on the left, or on the right
+// with -. Neither the width nor the precision can be over MAX_SIZE. There
+// are no tuples or lists yet, so a template takes exactly one value
+use super::{text, Fixed, RuntimeErrorKind, ValueKind};
+
+// The largest width or precision, so a template can't ask for a huge string
+pub const MAX_SIZE: usize = 1000;
+
+fn invalid(message: String) -> RuntimeErrorKind {
+    RuntimeErrorKind::Format(message)
+}
+
+fn size(digits: &str, what: &str) -> Result<usize, RuntimeErrorKind> {
+    digits.parse().ok()
+        .filter(|n| *n <= MAX_SIZE)
+        .ok_or_else(|| invalid(format!("the {} has to be a number up to {}, but got '{}'", what, MAX_SIZE, digits)))
+}
+
+fn integer(value: &ValueKind, directive: char) -> Result<String, RuntimeErrorKind> {
+    match value {
+        ValueKind::Integer(n) if directive == 'x' => Ok(format!("{:x}", n)),
+        ValueKind::Integer(n) => Ok(n.to_string()),
+        ValueKind::BigInt(n) if directive == 'd' => Ok(n.to_string()),
+        other => Err(invalid(format!("%{} needs an integer, but got {}", directive, other.type_name())))
+    }
+}
+
+fn decimal(value: &ValueKind, precision: Option<usize>) -> Result<String, RuntimeErrorKind> {
+    let places = precision.unwrap_or(6);
+
+    match value {
+        // Rounded from the exact value, so 2.675d is 2.68 at two places
+        ValueKind::Fixed(n) => Ok(round_fixed(**n, places as u32).unwrap_or_else(|| format!("{:.*}", places, n.to_f64()))),
+        other => match f64::try_from(other.clone()) {
+            Ok(n) => Ok(format!("{:.*}", places, n)),
+            Err(_) => Err(invalid(format!("%f needs a number, but got {}", other.type_name())))
+        }
+    }
+}
+
+// The exact decimal at `places` places, rounding half away from zero, or
+// None if that many places don't fit
+fn round_fixed(n: Fixed, places: u32) -> Option<String> {
+    let (units, scale) = n.parts();
+
+    let units = if scale > places {
+        let divisor = 10i128.checked_pow(scale - places)?;
+        let (quotient, remainder) = (units / divisor, units % divisor);
+        if remainder.unsigned_abs() * 2 >= divisor.unsigned_abs() { quotient + units.signum() } else { quotient }
+    } else {
+        units.checked_mul(10i128.checked_pow(places - scale)?)?
+    };
+
+    Some(Fixed::new(units, places).to_string())
+}
+
+fn pad(text: String, width: usize, left: bool) -> String {
+    if left {
+        format!("{:<width$}", text)
+    } else {
+        format!("{:>width$}", text)
+    }
+}
+
+pub fn format(template: &str, value: &ValueKind) -> Result<String, RuntimeErrorKind> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    let mut used = false;
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            result.push('%');
+            continue;
+        }
+
+        let left = chars.next_if_eq(&'-').is_some();
+
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            width.push(digit);
+        }
+
+        let width = if width.is_empty() { 0 } else { size(&width, "width")? };
+
+        let mut precision = None;
+        if chars.next_if_eq(&'.').is_some() {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            precision = Some(size(&digits, "precision")?);
+        }
+
+        if used {
+            return Err(invalid(String::from("the template has more directives than the one value")));
+        }
+        used = true;
+
+        let formatted = match chars.next() {
+            Some(directive @ ('d' | 'x')) => integer(value, directive)?,
+            Some('f') => decimal(value, precision)?,
+            Some('s') => {
+                let text = text(value);
+                match precision {
+                    Some(length) => text.chars().take(length).collect(),
+                    None => text
+                }
+            },
+            Some(other) => return Err(invalid(format!("unknown directive %{}", other))),
+            None => return Err(invalid(String::from("the template ends in the middle of a directive")))
+        };
+
+        result.push_str(&pad(formatted, width, left));
+    }
+
+    if !used {
+        return Err(invalid(String::from("the template has no directive for the value")));
+    }
+    Ok(result)
+}
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use super::analysis::{self, Warning};
+use super::parser::{Node, NodeVisitor, TokenKind};
+use super::{parse, Error, PRELUDE_NAMESPACE};
+
+// Every lint `lint` can report, by code
+pub const LINTS: &[&str] = &[
+    "unused-variable",
+    "dead-store",
+    "no-effect",
+    "use-before-assignment",
+    "shadowed-builtin",
+    "assigned-constant"
+];
+
+// Whether the prelude defines the variable, so every program starts with it
+fn is_predefined(name: &str) -> bool {
+    name.starts_with(PRELUDE_NAMESPACE)
+}
+
+struct Linter<'a> {
+    builtins: &'a [&'a str],
+    assigned: HashSet<String>,
+    // Variables already reported, so each one is only reported once
+    reported: HashSet<String>,
+    warnings: Vec<Warning>
+}
+
+impl NodeVisitor for Linter<'_> {
+    fn visit_assign(&mut self, _node: &Node, target: &Node, value: &Node) {
+        self.visit(value);
+
+        if let TokenKind::Identifier(name) = &target.entry {
+            if self.builtins.contains(&name.as_str()) {
+                self.warnings.push(Warning {
+                    code: "shadowed-builtin",
+                    message: format!("`{}` is already the name of a builtin", name),
+                    span: target.span
+                });
+            }
+
+            self.assigned.insert(name.clone());
+        }
+    }
+
+    fn visit_identifier(&mut self, node: &Node, name: &str) {
+        if !self.assigned.contains(name) && !is_predefined(name) && self.reported.insert(name.to_string()) {
+            self.warnings.push(Warning {
+                code: "use-before-assignment",
+                message: format!("Variable `{}` is used before it is assigned", name),
+                span: node.span
+            });
+        }
+    }
+}
+
+// The warnings of `check` along with stricter ones that only make sense for
+// a whole file, like reading a variable no earlier line assigned. `builtins`
+// are the functions the program can call, and lints whose code is in
+// `allowed` are left out
+pub fn lint(src: &str, builtins: &[&str], allowed: &[&str]) -> Result<Vec<Warning>, Error> {
+    let statements = parse(src)?;
+
+    let mut linter = Linter {
+        builtins,
+        assigned: HashSet::new(),
+        reported: HashSet::new(),
+        warnings: analysis::analyze(&statements)
+    };
+
+    for statement in &statements {
+        linter.visit(statement);
+    }
+
+    let mut warnings = linter.warnings;
+    warnings.retain(|w| !allowed.contains(&w.code));
+    warnings.sort_by_key(|w| (w.span.line, w.span.col));
+
+    Ok(warnings)
+}
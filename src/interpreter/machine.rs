@@ -0,0 +1,160 @@
+// Evaluation as a stack machine, for EvalMode::Stack. A statement is first
+// flattened into postfix operations, then they run in order against the
+// state's stack: leaves push their value, and operators and calls pop their
+// operands and push the result. Neither step recurses, so the operands an
+// expression needs at once are bounded by the stack size of the state
+// rather than the native stack. How deeply it nests is bounded by the
+// parser either way
+use super::parser::Node;
+use super::{assign, call, do_operation, do_unary, locate, visit_alone_node, writable};
+use super::{EvalResult, RuntimeError, RuntimeErrorKind, State, TokenKind};
+
+enum Op<'a> {
+    // A literal or a variable
+    Leaf(&'a Node),
+    Unary(&'a Node),
+    Binary(&'a Node),
+    // Checks the target of an assignment before its value is evaluated
+    Target(&'a Node, &'a str),
+    Assign(&'a Node, &'a str),
+    // Reports an assignment to something other than a name, whose value is
+    // on the stack
+    InvalidAssign(&'a Node),
+    // Calls the function with the arguments on the stack
+    Call(&'a Node, &'a str)
+}
+
+// Work left while flattening: a node still to flatten, or an operation to
+// emit once its operands are
+enum Work<'a> {
+    Node(&'a Node),
+    Op(Op<'a>)
+}
+
+// Children are pushed last first, so they are flattened in order
+fn flatten(statement: &Node) -> Result<Vec<Op<'_>>, RuntimeError> {
+    let mut ops = Vec::new();
+    let mut work = vec![Work::Node(statement)];
+
+    while let Some(item) = work.pop() {
+        let node = match item {
+            Work::Op(op) => {
+                ops.push(op);
+                continue;
+            },
+            Work::Node(node) => node
+        };
+
+        if node.children.is_empty() {
+            ops.push(Op::Leaf(node));
+            continue;
+        }
+
+        match (&node.entry, node.children.as_slice()) {
+            (TokenKind::Lparen, [callee, args @ ..]) => {
+                let TokenKind::Identifier(name) = &callee.entry else {
+                    return Err(locate(RuntimeErrorKind::UnexpectedNode(callee.entry.clone()), node));
+                };
+                work.push(Work::Op(Op::Call(node, name)));
+                work.extend(args.iter().rev().map(Work::Node));
+            },
+            (TokenKind::Assign, [target @ Node { entry: TokenKind::Identifier(name), .. }, value]) => {
+                work.push(Work::Op(Op::Assign(node, name)));
+                work.push(Work::Node(value));
+                work.push(Work::Op(Op::Target(target, name)));
+            },
+            (TokenKind::Assign, [target, _]) => {
+                work.push(Work::Op(Op::InvalidAssign(node)));
+                work.push(Work::Node(target));
+            },
+            (op, [operand]) if is_operator(op) => {
+                work.push(Work::Op(Op::Unary(node)));
+                work.push(Work::Node(operand));
+            },
+            (op, [lhs, rhs]) if is_operator(op) => {
+                work.push(Work::Op(Op::Binary(node)));
+                work.push(Work::Node(rhs));
+                work.push(Work::Node(lhs));
+            },
+            (other, _) => return Err(locate(RuntimeErrorKind::UnexpectedNode(other.clone()), node))
+        }
+    }
+
+    Ok(ops)
+}
+
+fn is_operator(entry: &TokenKind) -> bool {
+    matches!(
+        entry,
+        TokenKind::Plus | TokenKind::Minus | TokenKind::Asterisk | TokenKind::ForwardSlash | TokenKind::Percent |
+        TokenKind::IsEquals | TokenKind::NotEquals | TokenKind::Less | TokenKind::Greater
+    )
+}
+
+// Evaluates one statement. Whatever it left on the stack is dropped, even
+// if it fails partway
+pub fn eval(statement: &Node, state: &mut State) -> EvalResult {
+    let base = state.size_stack();
+    let result = execute(&flatten(statement)?, state);
+    if result.is_err() {
+        state.keep_failed_stack();
+    }
+    state.stack.split_off(base);
+    result
+}
+
+fn execute(ops: &[Op], state: &mut State) -> EvalResult {
+    for op in ops {
+        let value = match *op {
+            // Assignment targets aren't nodes that get evaluated, so they
+            // don't count as an operation
+            Op::Target(target, name) => {
+                writable(name, target, state)?;
+                continue;
+            },
+            Op::Leaf(node) => {
+                state.tick().map_err(|e| locate(e, node))?;
+                visit_alone_node(node, state)?
+            },
+            Op::Unary(node) => {
+                state.tick().map_err(|e| locate(e, node))?;
+                let n = state.pop_stack();
+                do_unary(n, node, state)?
+            },
+            Op::Binary(node) => {
+                state.tick().map_err(|e| locate(e, node))?;
+                let rhs = state.pop_stack();
+                let lhs = state.pop_stack();
+                do_operation(&lhs, &rhs, &node.entry, state).map_err(|e| locate(e, node))?
+            },
+            Op::Assign(node, name) => {
+                state.tick().map_err(|e| locate(e, node))?;
+                let symbol = node.children[0].symbol.unwrap_or_else(|| state.symbols.intern(name));
+                let value = state.pop_stack();
+                assign(symbol, value, &node.children[0], state)?
+            },
+            Op::InvalidAssign(node) => {
+                state.tick().map_err(|e| locate(e, node))?;
+                return Err(locate(RuntimeErrorKind::InvalidAssignment(state.pop_stack()), node));
+            },
+            Op::Call(node, name) => {
+                state.tick().map_err(|e| locate(e, node))?;
+                let base = state.size_stack() - (node.children.len() - 1);
+                let args = state.stack.split_off(base);
+                call(name, &args, node, state)?
+            }
+        };
+
+        state.push_stack(value).map_err(|e| locate(e, node_of(op)))?;
+    }
+
+    Ok(state.pop_stack())
+}
+
+// The node an operation came from, for locating its errors
+fn node_of<'a>(op: &Op<'a>) -> &'a Node {
+    match *op {
+        Op::Leaf(node) | Op::Unary(node) | Op::Binary(node) | Op::InvalidAssign(node) => node,
+        Op::Target(node, _) | Op::Assign(node, _) | Op::Call(node, _) => node
+    }
+}
@@ -0,0 +1,132 @@
+use super::parser::{Node, NodeFolder, TokenKind};
+use super::{do_operation, State, ValueKind};
+
+// The literal a folded value is written as, if it has one
+fn literal(value: ValueKind) -> Option<TokenKind> {
+    match value {
+        ValueKind::Integer(n) => Some(TokenKind::Integer(n)),
+        ValueKind::Decimal(n) => Some(TokenKind::Decimal(n)),
+        ValueKind::BigInt(n) => Some(TokenKind::BigInt(n)),
+        ValueKind::Fixed(n) => Some(TokenKind::Fixed(n)),
+        ValueKind::Boolean(b) => Some(TokenKind::Boolean(b)),
+        ValueKind::None => Some(TokenKind::None),
+        ValueKind::Str(s) => Some(TokenKind::QuotedString(s.to_string())),
+        ValueKind::Bytes(bytes) => Some(TokenKind::Bytes(bytes.to_vec())),
+        _ => None
+    }
+}
+
+fn constant(node: &Node) -> Option<ValueKind> {
+    match &node.entry {
+        TokenKind::Integer(n) if node.children.is_empty() => Some(ValueKind::Integer(*n)),
+        TokenKind::Decimal(n) if node.children.is_empty() => Some(ValueKind::Decimal(*n)),
+        TokenKind::BigInt(n) if node.children.is_empty() => Some(ValueKind::BigInt(n.clone())),
+        TokenKind::Fixed(n) if node.children.is_empty() => Some(ValueKind::Fixed(n.clone())),
+        TokenKind::Boolean(b) if node.children.is_empty() => Some(ValueKind::Boolean(*b)),
+        TokenKind::None if node.children.is_empty() => Some(ValueKind::None),
+        TokenKind::QuotedString(s) if node.children.is_empty() => Some(ValueKind::Str(s.as_str().into())),
+        TokenKind::Bytes(bytes) if node.children.is_empty() => Some(ValueKind::Bytes(bytes.as_slice().into())),
+        _ => None
+    }
+}
+
+// A transformation of the parsed statements before they run. It must not
+// change what the program does
+pub trait AstPass: Send + Sync {
+    // Identifies the pass on the command line
+    fn name(&self) -> &'static str;
+    fn run(&mut self, statements: &mut [Node], state: &State);
+}
+
+pub struct ConstantFolding;
+
+impl AstPass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "constant-folding"
+    }
+
+    fn run(&mut self, statements: &mut [Node], state: &State) {
+        for statement in statements {
+            fold_constants(statement, state);
+        }
+    }
+}
+
+// Runs the registered passes in order. Passes can be switched off without
+// losing their place in the order
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<(Box<dyn AstPass>, bool)>
+}
+
+impl PassManager {
+    // The passes of an optimization level, like -O1 on the command line.
+    // Level 0 runs nothing
+    pub fn for_level(level: u8) -> PassManager {
+        let mut manager = PassManager::default();
+
+        if level >= 1 {
+            manager.register(Box::new(ConstantFolding));
+        }
+
+        manager
+    }
+
+    // Adds the pass after all the others, enabled
+    pub fn register(&mut self, pass: Box<dyn AstPass>) {
+        self.passes.push((pass, true));
+    }
+
+    // Returns false if there is no pass with that name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.passes.iter_mut().find(|(pass, _)| pass.name() == name) {
+            Some((_, on)) => {
+                *on = enabled;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn run(&mut self, statements: &mut [Node], state: &State) {
+        for (pass, enabled) in &mut self.passes {
+            if *enabled {
+                pass.run(statements, state);
+            }
+        }
+    }
+}
+
+// Replaces operators whose operands are all constants with their result,
+// bottom up, so `2 * (3 + 4)` becomes `14`. The overflow policy and strict
+// mode of `state` apply just like at runtime. Anything that would fail, like
+// a division by zero, is left alone for evaluation to report
+pub fn fold_constants(node: &mut Node, state: &State) {
+    Folder { state }.fold(node);
+}
+
+struct Folder<'a> {
+    state: &'a State
+}
+
+impl NodeFolder for Folder<'_> {
+    fn fold_node(&mut self, node: &mut Node) {
+        if !matches!(node.entry, TokenKind::Plus | TokenKind::Minus | TokenKind::Asterisk | TokenKind::ForwardSlash | TokenKind::Percent |
+            TokenKind::IsEquals | TokenKind::NotEquals | TokenKind::Less | TokenKind::Greater) {
+            return;
+        }
+
+        let folded = match node.children.as_slice() {
+            [lhs, rhs] => match (constant(lhs), constant(rhs)) {
+                (Some(lhs), Some(rhs)) => do_operation(&lhs, &rhs, &node.entry, self.state).ok().and_then(literal),
+                _ => None
+            },
+            _ => None
+        };
+
+        if let Some(entry) = folded {
+            node.entry = entry;
+            node.children.clear();
+        }
+    }
+}
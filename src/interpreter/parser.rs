@@ -1,149 +1,379 @@
 pub mod lex;
+mod serialize;
+pub mod visit;
 
 pub use lex::lex::TokenKind;
 pub use lex::lex::lex;
+pub use lex::lex::{LexError, LexErrorKind, Lexer, Span, Token};
+pub use visit::{NodeFolder, NodeVisitor};
+
+use super::{Error, Symbol, DEFAULT_MAX_DEPTH};
 
 #[derive(Debug)]
 pub struct Node {
     pub children: Vec<Node>,
-    pub entry: TokenKind
+    pub entry: TokenKind,
+    pub span: Span,
+    // The variable an identifier names, filled in before evaluation so
+    // reading or writing it doesn't look the name up
+    pub symbol: Option<Symbol>
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span
+}
+
+impl ParseError {
+    fn new(message: String, span: Span) -> ParseError {
+        ParseError { message, span }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
 }
 
 impl Node {
     pub fn new() -> Node {
         Node {
             children: Vec::new(),
-            entry: TokenKind::Lparen
+            entry: TokenKind::Lparen,
+            span: Span::default(),
+            symbol: None
+        }
+    }
+
+    // Creates an operator node spanning both of its operands
+    fn binary(entry: TokenKind, lhs: Node, rhs: Node) -> Node {
+        Node {
+            span: lhs.span.merge(rhs.span),
+            children: vec![lhs, rhs],
+            entry,
+            symbol: None
         }
     }
 }
 
-fn parse_expr(tokens: &Vec<TokenKind>, pos: usize) -> Result<(Node, usize), String> {
-    let (node_summand, next_pos) = parse_summand(tokens, pos)?;
+impl Default for Node {
+    fn default() -> Node {
+        Node::new()
+    }
+}
 
-    let t = tokens.get(next_pos);
+// Dropping the children one by one from a list instead of recursively, so
+// even a tree too deep to be parsed can be thrown away
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(&mut self.children);
+        while let Some(mut node) = pending.pop() {
+            pending.append(&mut node.children);
+        }
+    }
+}
 
-    if let Some(tk) = t {
-        let mut new_node = Node::new();
+type ParseResult = Result<(Node, usize), ParseError>;
 
-        match tk {
-            &TokenKind::Plus => { new_node.entry = TokenKind::Plus; }
-            &TokenKind::Minus => { new_node.entry = TokenKind::Minus; }
-            &TokenKind::Assign => { new_node.entry = TokenKind::Assign; }
-            &TokenKind::IsEquals => { new_node.entry = TokenKind::IsEquals; }
-            _ => return Ok((node_summand, next_pos))
-        };
+fn too_deep(span: Span) -> ParseError {
+    ParseError::new(String::from("Expressions nest too deeply"), span)
+}
 
-        new_node.children.push(node_summand);
-        let (rhs, i) = parse_expr(tokens, next_pos + 1)?;
-        new_node.children.push(rhs);
-        Ok((new_node, i))
-    } else {
-        Ok((node_summand, next_pos))
-    }
+// The position right after the last token, where a missing token would be
+fn eof_span(tokens: &[Token]) -> Span {
+    tokens.last().map(|t| t.span.end()).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Assoc {
+    Left,
+    Right
 }
 
-fn parse_summand(tokens: &Vec<TokenKind>, pos: usize) -> Result<(Node, usize), String> {
-    let (node_term, next_pos) = parse_term(tokens, pos)?;
+// The precedence table: how tightly each binary operator binds, higher
+// binding tighter, and which way a chain of equally tight ones groups.
+// Adding an operator takes an entry here and an arm in the evaluator
+fn infix_operator(kind: &TokenKind) -> Option<(u8, Assoc)> {
+    match kind {
+        TokenKind::Assign => Some((1, Assoc::Right)),
+        TokenKind::IsEquals | TokenKind::NotEquals | TokenKind::Less | TokenKind::Greater => Some((2, Assoc::Left)),
+        TokenKind::Plus | TokenKind::Minus => Some((3, Assoc::Left)),
+        TokenKind::Asterisk | TokenKind::ForwardSlash | TokenKind::Percent => Some((4, Assoc::Left)),
+        _ => None
+    }
+}
 
-    let t = tokens.get(next_pos);
+// Unary + and - bind tighter than any binary operator
+const PREFIX_PRECEDENCE: u8 = 5;
 
-    let mut new_node = Node::new();
+// `depth` is how many more levels the parser may recurse. Every nested
+// expression, parenthesized or not, passes through parse_binary, which
+// takes one level
+fn parse_expr(tokens: &[Token], pos: usize, depth: usize) -> ParseResult {
+    parse_binary(tokens, pos, 0, depth)
+}
 
-    match t {
-        Some(&TokenKind::Asterisk) => { new_node.entry = TokenKind::Asterisk; },
-        Some(&TokenKind::ForwardSlash) => { new_node.entry = TokenKind::ForwardSlash; },
-        _ => return Ok((node_term, next_pos))
+// Parses an expression made of operators binding at least as tightly as
+// `min_precedence`
+fn parse_binary(tokens: &[Token], pos: usize, min_precedence: u8, depth: usize) -> ParseResult {
+    let Some(depth) = depth.checked_sub(1) else {
+        return Err(too_deep(tokens.get(pos).map_or_else(|| eof_span(tokens), |t| t.span)));
     };
 
-    new_node.children.push(node_term);
-    let (rhs, i) = parse_summand(tokens, next_pos + 1)?;
-    new_node.children.push(rhs);
-    Ok((new_node, i))
+    let (mut lhs, mut pos) = parse_term(tokens, pos, depth)?;
+
+    while let Some(op) = tokens.get(pos) {
+        let (precedence, assoc) = match infix_operator(&op.kind) {
+            Some((precedence, assoc)) if precedence >= min_precedence => (precedence, assoc),
+            _ => break
+        };
+
+        // A left associative operator doesn't take an equally tight one as
+        // its right operand, so the chain continues in this loop instead
+        let next_min = match assoc {
+            Assoc::Left => precedence + 1,
+            Assoc::Right => precedence
+        };
+
+        let (rhs, next_pos) = parse_binary(tokens, pos + 1, next_min, depth)?;
+        lhs = Node::binary(op.kind.clone(), lhs, rhs);
+        pos = next_pos;
+    }
+
+    Ok((lhs, pos))
 }
 
-fn parse_term(tokens: &Vec<TokenKind>, pos: usize) -> Result<(Node, usize), String> {
-    let t = tokens.get(pos).ok_or(String::from("Unexpected EOF, expected paren or number"))?;
+// The token as the entry of a leaf node, if it is a literal
+fn literal(kind: &TokenKind) -> Option<TokenKind> {
+    match kind {
+        TokenKind::Integer(_) | TokenKind::Decimal(_) | TokenKind::BigInt(_) | TokenKind::Fixed(_) |
+        TokenKind::Boolean(_) | TokenKind::None | TokenKind::QuotedString(_) | TokenKind::Bytes(_) => Some(kind.clone()),
+        _ => None
+    }
+}
 
-    match &*t {
-        TokenKind::Integer(n) => {
-            let mut node = Node::new();
-            node.entry = TokenKind::Integer(n.to_owned());
-            Ok((node, pos + 1))
-        }
-        TokenKind::Decimal(n) => {
-            let mut node = Node::new();
-            node.entry = TokenKind::Decimal(n.to_owned());
-            Ok((node, pos + 1))
-        }
-        TokenKind::Boolean(b) => {
-            let mut node = Node::new();
-            node.entry = TokenKind::Boolean(b.to_owned());
-            Ok((node, pos + 1))
-        }
-        TokenKind::QuotedString(s) => {
-            let mut node = Node::new();
-            node.entry = TokenKind::QuotedString(s.to_owned());
-            Ok((node, pos + 1))
-        }
+fn parse_term(tokens: &[Token], pos: usize, depth: usize) -> ParseResult {
+    let t = tokens.get(pos).ok_or_else(|| {
+        ParseError::new(String::from("Unexpected EOF, expected paren or number"), eof_span(tokens))
+    })?;
+
+    let mut node = Node::new();
+    node.span = t.span;
+
+    if let Some(entry) = literal(&t.kind) {
+        node.entry = entry;
+        return Ok((node, pos + 1));
+    }
+
+    match &t.kind {
         TokenKind::Identifier(name) => {
-            let mut node = Node::new();
             node.entry = TokenKind::Identifier(name.to_owned());
-            Ok((node, pos + 1))
+
+            if let Some(TokenKind::Lparen) = tokens.get(pos + 1).map(|t| &t.kind) {
+                parse_call(tokens, pos + 2, node, depth)
+            } else {
+                Ok((node, pos + 1))
+            }
         }
         TokenKind::Lparen => {
-            parse_expr(tokens, pos + 1).and_then(|(node, next_pos)| {
+            // Running out of tokens is reported at the ( that was never closed
+            if pos + 1 >= tokens.len() {
+                return Err(ParseError::new(String::from("Unclosed (, expected an expression and )"), t.span));
+            }
+
+            parse_expr(tokens, pos + 1, depth).and_then(|(node, next_pos)| {
                 if let Some(tok) = tokens.get(next_pos) {
-                    if let TokenKind::Rparen = tok {
-                        return Ok((node, next_pos + 1))
+                    if let TokenKind::Rparen = tok.kind {
+                        Ok((node, next_pos + 1))
                     } else {
-                        Err(format!("Expected ) but found {:?} at {}", tok, next_pos))
+                        Err(ParseError::new(format!("Expected ) but found {}", tok.kind), tok.span))
                     }
                 } else {
-                    Err(format!("Expected ) but found {:#?} at {}", tokens.get(next_pos), next_pos))
+                    Err(ParseError::new(String::from("Unclosed (, expected ) before the end of the statement"), t.span))
                 }
             })
         }
         TokenKind::Plus => {
-            parse_expr(tokens, pos + 1).and_then(|(node, next_pos)| {
+            parse_binary(tokens, pos + 1, PREFIX_PRECEDENCE, depth).map(|(operand, next_pos)| {
                 // 0 + node
-                let mut unary = Node::new();
-                unary.entry = TokenKind::Plus;
-                unary.children.push(Node {
-                    children: Vec::new(),
-                    entry: TokenKind::Integer(0)
-                });
-                unary.children.push(node);
-
-                return Ok((unary, next_pos))
+                node.entry = TokenKind::Integer(0);
+                (Node::binary(TokenKind::Plus, node, operand), next_pos)
             })
         }
         TokenKind::Minus => {
-            parse_summand(tokens, pos + 1).and_then(|(node, next_pos)| {
+            parse_binary(tokens, pos + 1, PREFIX_PRECEDENCE, depth).map(|(operand, next_pos)| {
                 // 0 - node
-                let mut unary = Node::new();
-                unary.entry = TokenKind::Minus;
-                unary.children.push(Node {
-                    children: Vec::new(),
-                    entry: TokenKind::Integer(0)
-                });
-                unary.children.push(node);
-
-                return Ok((unary, next_pos))
+                node.entry = TokenKind::Integer(0);
+                (Node::binary(TokenKind::Minus, node, operand), next_pos)
             })
         }
-        _ => {
-            Err(format!("Unexpected token {:?} at {}", t, pos))
+        other => {
+            Err(ParseError::new(format!("Unexpected token `{}`", other), t.span))
+        }
+    }
+}
+
+// Parses the arguments of a call up to the closing paren, starting right
+// after the opening one. A call is a ( node whose first child is the callee
+// and the rest are the arguments
+fn parse_call(tokens: &[Token], mut pos: usize, callee: Node, depth: usize) -> ParseResult {
+    let lparen = tokens[pos - 1].span;
+    let mut call = Node::new();
+    call.span = callee.span;
+    call.children.push(callee);
+
+    if let Some(TokenKind::Rparen) = tokens.get(pos).map(|t| &t.kind) {
+        call.span = call.span.merge(tokens[pos].span);
+        return Ok((call, pos + 1));
+    }
+
+    loop {
+        let (arg, next_pos) = parse_expr(tokens, pos, depth)?;
+        call.children.push(arg);
+
+        match tokens.get(next_pos) {
+            Some(Token { kind: TokenKind::Comma, .. }) => pos = next_pos + 1,
+            Some(Token { kind: TokenKind::Rparen, span }) => {
+                call.span = call.span.merge(*span);
+                return Ok((call, next_pos + 1));
+            },
+            Some(tok) => return Err(ParseError::new(format!("Expected , or ) but found {}", tok.kind), tok.span)),
+            None => return Err(ParseError::new(String::from("Unclosed (, expected ) before the end of the statement"), lparen))
+        }
+    }
+}
+
+pub fn parse(tokens: &[Token]) -> Result<Node, ParseError> {
+    parse_limited(tokens, DEFAULT_MAX_DEPTH)
+}
+
+// Like `parse`, failing if the tree would be more than `max_depth` nodes
+// deep. Every later step walks the tree recursively, so this is what keeps
+// deeply nested source from overflowing the native stack
+pub fn parse_limited(tokens: &[Token], max_depth: usize) -> Result<Node, ParseError> {
+    let node = parse_expr(tokens, 0, max_depth).and_then(|(n, i)| match tokens.get(i) {
+        None => Ok(n),
+        Some(Token { kind: TokenKind::Rparen, span }) => Err(ParseError::new(String::from("Unmatched ), there is no ( to close"), *span)),
+        Some(tok) => Err(ParseError::new(format!("Expected EOF, happened on `{}`", tok.kind), tok.span))
+    })?;
+
+    match too_deep_at(&node, max_depth) {
+        Some(span) => Err(too_deep(span)),
+        None => Ok(node)
+    }
+}
+
+// Where the tree goes deeper than `max_depth`, if it does. A chain of left
+// associative operators is built in a loop rather than by recursing, so its
+// depth is only known once it is done. Walks with a list of its own, as the
+// tree may be too deep to recurse into
+fn too_deep_at(node: &Node, max_depth: usize) -> Option<Span> {
+    let mut pending = vec![(node, 1)];
+
+    while let Some((node, depth)) = pending.pop() {
+        if depth > max_depth {
+            return Some(node.span);
+        }
+        pending.extend(node.children.iter().map(|child| (child, depth + 1)));
+    }
+
+    None
+}
+
+// Whether a statement ending in this token goes on after a newline, like
+// `1 +` or `f(1,`
+fn continues(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Comma) || infix_operator(kind).is_some()
+}
+
+// Splits the tokens into statements, separated by semicolons and by newlines
+// outside of parens. A newline after an operator or comma doesn't end the
+// statement either. Also returns whether the last statement is unfinished,
+// i.e. has an unclosed paren or ends where it has to go on
+fn split_statements(tokens: &[Token]) -> (Vec<Vec<Token>>, bool) {
+    let mut statements = Vec::new();
+    let mut current: Vec<Token> = Vec::new();
+    let mut depth = 0usize;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Semicolon => {
+                statements.push(std::mem::take(&mut current));
+                depth = 0;
+                continue;
+            },
+            TokenKind::NewLine => {
+                if depth == 0 && !current.last().is_some_and(|t| continues(&t.kind)) {
+                    statements.push(std::mem::take(&mut current));
+                }
+                continue;
+            },
+            TokenKind::Lparen => depth += 1,
+            TokenKind::Rparen => depth = depth.saturating_sub(1),
+            _ => {}
         }
+
+        current.push(token.clone());
     }
+
+    let unfinished = depth > 0 || current.last().is_some_and(|t| continues(&t.kind));
+    statements.push(current);
+    statements.retain(|s| !s.is_empty());
+
+    (statements, unfinished)
+}
+
+// Parses statements separated by newlines or semicolons. A statement with an
+// error is skipped, so every error in the source gets reported at once along
+// with the statements that did parse
+pub fn parse_program(tokens: &[Token]) -> (Vec<Node>, Vec<ParseError>) {
+    parse_program_limited(tokens, DEFAULT_MAX_DEPTH)
 }
 
-pub fn parse(src: &str) -> Result<Node, String> {
-    let tokens = lex(src)?;
+// Like `parse_program`, with `parse_limited` for every statement
+pub fn parse_program_limited(tokens: &[Token], max_depth: usize) -> (Vec<Node>, Vec<ParseError>) {
+    let mut nodes = Vec::new();
+    let mut errors = Vec::new();
+
+    for statement in split_statements(tokens).0 {
+        match parse_limited(&statement, max_depth) {
+            Ok(node) => nodes.push(node),
+            Err(e) => errors.push(e)
+        }
+    }
 
-    parse_expr(&tokens, 0).and_then(|(n, i)| if i >= tokens.len() {
-        Ok(n)
-    } else {
-        Err(format!("Expected EOF, happened on {:?} at {}", tokens[i], i))
-    })
-}
\ No newline at end of file
+    (nodes, errors)
+}
+
+// What parsing a piece of input that may go on in the next line gave
+#[derive(Debug)]
+pub enum Partial {
+    // Every statement in the input
+    Complete(Vec<Node>),
+    // The input stops in the middle of a statement or string, so more lines
+    // are needed before it can be parsed
+    Incomplete,
+    // The input is wrong no matter what follows
+    Error(Error)
+}
+
+// Parses input as it is typed, telling input that needs more lines, like an
+// unclosed paren or string, apart from input with real errors
+pub fn parse_partial(src: &str) -> Partial {
+    let tokens = match lex(src) {
+        Ok(tokens) => tokens,
+        Err(e) if e.kind == LexErrorKind::UnterminatedString => return Partial::Incomplete,
+        Err(e) => return Partial::Error(Error::Lex(e))
+    };
+
+    if split_statements(&tokens).1 {
+        return Partial::Incomplete;
+    }
+
+    match parse_program(&tokens) {
+        (statements, errors) if errors.is_empty() => Partial::Complete(statements),
+        (_, errors) => Partial::Error(Error::Syntax(errors))
+    }
+}
@@ -1,19 +1,35 @@
+#[allow(clippy::module_inception)]
 pub mod lex {
-    #[derive(Debug)]
+    use std::sync::Arc;
+
+    use crate::interpreter::bigint::BigInt;
+    use crate::interpreter::fixed::Fixed;
+
+    #[derive(Debug, Clone)]
     pub enum TokenKind {
         Integer(i64),
         Decimal(f64),
+        // An integer literal with an `n` suffix, like 10n
+        BigInt(Arc<BigInt>),
+        // A number literal with a `d` suffix, like 1.10d
+        Fixed(Arc<Fixed>),
         Identifier(String),
         QuotedString(String),
+        // A byte string literal, like b"GIF\x00"
+        Bytes(Vec<u8>),
         Boolean(bool),
+        None,
         Plus,
         Minus,
         Asterisk,
         ForwardSlash,
+        Percent,
         Dot,
         Assign,
         Lparen,
         Rparen,
+        Semicolon,
+        Comma,
         If,
         While,
         NewLine,
@@ -24,6 +40,126 @@ pub mod lex {
         Not
     }
 
+    // Writes the token the way it appears in source
+    impl std::fmt::Display for TokenKind {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                TokenKind::Integer(n) => write!(f, "{}", n),
+                TokenKind::Decimal(n) => write!(f, "{}", n),
+                TokenKind::BigInt(n) => write!(f, "{}n", n),
+                TokenKind::Fixed(n) => write!(f, "{}d", n),
+                TokenKind::Identifier(name) => write!(f, "{}", name),
+                TokenKind::QuotedString(s) => write!(f, "{:?}", s),
+                TokenKind::Bytes(bytes) => write!(f, "{}", escape_bytes(bytes)),
+                TokenKind::Boolean(b) => write!(f, "{}", b),
+                TokenKind::None => write!(f, "none"),
+                TokenKind::Plus => write!(f, "+"),
+                TokenKind::Minus => write!(f, "-"),
+                TokenKind::Asterisk => write!(f, "*"),
+                TokenKind::ForwardSlash => write!(f, "/"),
+                TokenKind::Percent => write!(f, "%"),
+                TokenKind::Dot => write!(f, "."),
+                TokenKind::Assign => write!(f, "="),
+                TokenKind::Lparen => write!(f, "("),
+                TokenKind::Rparen => write!(f, ")"),
+                TokenKind::Semicolon => write!(f, ";"),
+                TokenKind::Comma => write!(f, ","),
+                TokenKind::If => write!(f, "if"),
+                TokenKind::While => write!(f, "while"),
+                TokenKind::NewLine => write!(f, "newline"),
+                TokenKind::Less => write!(f, "<"),
+                TokenKind::Greater => write!(f, ">"),
+                TokenKind::IsEquals => write!(f, "=="),
+                TokenKind::NotEquals => write!(f, "!="),
+                TokenKind::Not => write!(f, "!")
+            }
+        }
+    }
+
+    // Location of a piece of source. `line` and `col` are 1-based, and `len`
+    // is counted in characters
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub struct Span {
+        pub line: usize,
+        pub col: usize,
+        pub len: usize
+    }
+
+    impl Span {
+        // The smallest span covering both. Spans can't cross lines, so when
+        // `other` starts on a later line the result stops at the end of `self`
+        pub fn merge(self, other: Span) -> Span {
+            if self.line != other.line {
+                return self;
+            }
+
+            let col = self.col.min(other.col);
+            let end = (self.col + self.len).max(other.col + other.len);
+            Span { line: self.line, col, len: end - col }
+        }
+
+        // The empty span right after this one
+        pub fn end(self) -> Span {
+            Span { line: self.line, col: self.col + self.len, len: 0 }
+        }
+    }
+
+    impl std::fmt::Display for Span {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{}:{}", self.line, self.col)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Token {
+        pub kind: TokenKind,
+        pub span: Span
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum LexErrorKind {
+        UnknownCharacter(char),
+        IdentifierStartsWithDigit,
+        InvalidNumber(String),
+        InvalidEscape(String),
+        UnterminatedString,
+        UnexpectedEof
+    }
+
+    impl LexErrorKind {
+        // A stable name for the error, for tools that read diagnostics
+        pub fn code(&self) -> &'static str {
+            match self {
+                LexErrorKind::UnknownCharacter(_) => "unknown-character",
+                LexErrorKind::IdentifierStartsWithDigit => "identifier-starts-with-digit",
+                LexErrorKind::InvalidNumber(_) => "invalid-number",
+                LexErrorKind::InvalidEscape(_) => "invalid-escape",
+                LexErrorKind::UnterminatedString => "unterminated-string",
+                LexErrorKind::UnexpectedEof => "unexpected-eof"
+            }
+        }
+    }
+
+    // `span` points at the offending character
+    #[derive(Debug, Clone)]
+    pub struct LexError {
+        pub kind: LexErrorKind,
+        pub span: Span
+    }
+
+    impl std::fmt::Display for LexError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match &self.kind {
+                LexErrorKind::UnknownCharacter(c) => write!(f, "Unknown character '{}'", c),
+                LexErrorKind::IdentifierStartsWithDigit => write!(f, "Identifiers can't start with a number"),
+                LexErrorKind::InvalidNumber(n) => write!(f, "Can not parse number '{}'", n),
+                LexErrorKind::InvalidEscape(e) => write!(f, "Unknown escape '{}', expected \\xNN, \\\\ or \\\"", e),
+                LexErrorKind::UnterminatedString => write!(f, "String is never closed, expected a closing \""),
+                LexErrorKind::UnexpectedEof => write!(f, "Unexpected EOF")
+            }
+        }
+    }
+
     impl From<i64> for TokenKind {
         fn from(other: i64) -> TokenKind {
             TokenKind::Integer(other)
@@ -42,36 +178,58 @@ pub mod lex {
         }
     }
 
-    struct Lexer<'a> {
+    // Produces tokens one at a time as an iterator, e.g. for highlighters
+    // that don't need the whole token list. Iteration stops after an error
+    pub struct Lexer<'a> {
+        src: &'a str,
         current: usize,
-        remaining: &'a str
+        remaining: &'a str,
+        line: usize,
+        // Byte offset the current line starts at
+        line_start: usize,
+        failed: bool
     }
 
     impl<'a> Lexer<'a> {
-        fn new(src: &str) -> Lexer {
+        pub fn new(src: &str) -> Lexer<'_> {
             Lexer {
+                src,
                 current: 0,
-                remaining: src
+                remaining: src,
+                line: 1,
+                line_start: 0,
+                failed: false
             }
         }
 
-        fn next_token(&mut self) -> Result<Option<(TokenKind, usize, usize)>, i32> {
+        fn next_token(&mut self) -> Result<Option<Token>, LexError> {
             self.skip_whitespace();
 
             if self.remaining.is_empty() {
                 Ok(None)
             } else {
                 let start = self.current;
-                let tok = self._next_token().expect("Could not read the next token.");
-                Ok(Some((tok, start, self.current)))
+                let (line, col) = (self.line, self.column(start));
+
+                let kind = self._next_token().map_err(|kind| LexError {
+                    kind,
+                    span: Span { line, col, len: 1 }
+                })?;
+
+                let len = self.src[start..self.current].chars().count();
+                Ok(Some(Token { kind, span: Span { line, col, len } }))
             }
         }
 
+        fn column(&self, offset: usize) -> usize {
+            self.src[self.line_start..offset].chars().count() + 1
+        }
+
         fn skip_whitespace(&mut self) {
             self.chomp(skip(self.remaining))
         }
 
-        fn _next_token(&mut self) -> Result<TokenKind, usize> {
+        fn _next_token(&mut self) -> Result<TokenKind, LexErrorKind> {
             let (tok, bytes_read) = lex_one(self.remaining)?;
             self.chomp(bytes_read);
 
@@ -79,12 +237,33 @@ pub mod lex {
         }
 
         fn chomp(&mut self, num_bytes: usize) {
+            let chomped = &self.remaining[..num_bytes];
+
+            if let Some(i) = chomped.rfind('\n') {
+                self.line += chomped.matches('\n').count();
+                self.line_start = self.current + i + 1;
+            }
+
             self.remaining = &self.remaining[num_bytes..];
             self.current += num_bytes;
         }
     }
 
-    fn take_while<F>(data: &str, mut pred: F) -> Result<(&str, usize), usize> where F: FnMut(char) -> bool {
+    impl Iterator for Lexer<'_> {
+        type Item = Result<Token, LexError>;
+
+        fn next(&mut self) -> Option<Result<Token, LexError>> {
+            if self.failed {
+                return None;
+            }
+
+            let next = self.next_token();
+            self.failed = next.is_err();
+            next.transpose()
+        }
+    }
+
+    fn take_while<F>(data: &str, mut pred: F) -> Result<(&str, usize), LexErrorKind> where F: FnMut(char) -> bool {
         let mut current: usize = 0;
         
         for c in data.chars() {
@@ -96,30 +275,55 @@ pub mod lex {
         }
 
         if current == 0 {
-            Err(0)
+            Err(LexErrorKind::UnexpectedEof)
         } else {
             Ok((&data[..current], current))
         }
     }
 
-    fn lex_ident(data: &str) -> Result<(TokenKind, usize), usize> {
+    // Words that are tokens of their own. Only a whole identifier matches,
+    // so iffy and std.if are names
+    const KEYWORDS: [(&str, TokenKind); 5] = [
+        ("if", TokenKind::If),
+        ("while", TokenKind::While),
+        ("true", TokenKind::Boolean(true)),
+        ("false", TokenKind::Boolean(false)),
+        ("none", TokenKind::None)
+    ];
+
+    fn lex_ident(data: &str) -> Result<(TokenKind, usize), LexErrorKind> {
         match data.chars().next() {
-            Some(c) if c.is_digit(10) => panic!("Identifiers can't start with a number"),
-            None => panic!("Unexpected EOF"),
+            Some(c) if c.is_ascii_digit() => return Err(LexErrorKind::IdentifierStartsWithDigit),
+            None => return Err(LexErrorKind::UnexpectedEof),
             _ => {}
         }
 
-        let (got, bytes_read) = take_while(data, |c| c == '_' || c.is_alphanumeric())?;
+        let (_, mut bytes_read) = take_while(data, |c| c == '_' || c.is_alphanumeric())?;
+
+        // Qualified names like std.pi are one identifier, as long as a
+        // letter follows each dot
+        while let Some(rest) = data[bytes_read..].strip_prefix('.') {
+            match rest.chars().next() {
+                Some(c) if c == '_' || c.is_alphabetic() => {
+                    bytes_read += 1 + take_while(rest, |c| c == '_' || c.is_alphanumeric())?.1;
+                },
+                _ => break
+            }
+        }
+
+        let got = &data[..bytes_read];
 
-        let tok = TokenKind::Identifier(got.to_string());
+        let tok = KEYWORDS.iter()
+            .find(|(keyword, _)| *keyword == got)
+            .map_or_else(|| TokenKind::Identifier(got.to_string()), |(_, kind)| kind.clone());
         Ok((tok, bytes_read))
     }
 
-    fn lex_number(data: &str) -> Result<(TokenKind, usize), usize> {
+    fn lex_number(data: &str) -> Result<(TokenKind, usize), LexErrorKind> {
         let mut was_dot = false;
 
         let (decimal, bytes_read) = take_while(data, |c| {
-            if c.is_digit(10) {
+            if c.is_ascii_digit() {
                 true
             } else if c == '.' {
                 if !was_dot {
@@ -133,16 +337,30 @@ pub mod lex {
             }
         })?;
 
-        if was_dot {
-            let n: f64 = decimal.parse().expect("Can not parse float number.");
+        let invalid = || LexErrorKind::InvalidNumber(decimal.to_string());
+
+        // A suffix only counts when no identifier follows right away
+        let rest = &data[bytes_read..];
+        let suffix = rest.chars().next()
+            .filter(|c| matches!(c, 'n' | 'd'))
+            .filter(|_| !rest[1..].starts_with(|c: char| c == '_' || c.is_alphanumeric()));
+
+        if suffix == Some('d') {
+            let n: Fixed = decimal.parse().map_err(|_| invalid())?;
+            Ok((TokenKind::Fixed(Arc::new(n)), bytes_read + 1))
+        } else if was_dot {
+            let n: f64 = decimal.parse().map_err(|_| invalid())?;
             Ok((TokenKind::Decimal(n), bytes_read))
+        } else if suffix == Some('n') {
+            let n: BigInt = decimal.parse().map_err(|_| invalid())?;
+            Ok((TokenKind::BigInt(Arc::new(n)), bytes_read + 1))
         } else {
-            let n: i64 = decimal.parse().expect("Can not parse float number.");
+            let n: i64 = decimal.parse().map_err(|_| invalid())?;
             Ok((TokenKind::Integer(n), bytes_read))
         }
     }
 
-    fn lex_string(data: &str) -> Result<(TokenKind, usize), usize> {
+    fn lex_string(data: &str) -> Result<(TokenKind, usize), LexErrorKind> {
         let mut was_first = false;
 
         let (string, bytes_read) = take_while(data, |c| {
@@ -154,50 +372,122 @@ pub mod lex {
             }
         })?;
 
+        // The error points at the opening quote
+        if !data[bytes_read..].starts_with('"') {
+            return Err(LexErrorKind::UnterminatedString);
+        }
+
         let mut result = String::from(string);
         result.remove(0);
 
         Ok((TokenKind::QuotedString(result), bytes_read + 1))
     }
 
-    fn lex_equals(data: String) -> (TokenKind, usize) {
-        if data.as_bytes()[1] as char == '=' {
-            (TokenKind::IsEquals, 2)
-        } else {
-            (TokenKind::Assign, 1)
+    // Byte strings are text, where \xNN stands for any byte and \\ and \"
+    // for a backslash and a quote
+    fn lex_bytes(data: &str) -> Result<(TokenKind, usize), LexErrorKind> {
+        let mut bytes = Vec::new();
+        let mut chars = data.char_indices().skip(2);
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((TokenKind::Bytes(bytes), i + 1)),
+                '\\' => match chars.next() {
+                    Some((_, c @ ('\\' | '"'))) => bytes.push(c as u8),
+                    Some((j, 'x')) => {
+                        let byte = data.get(j + 1..j + 3)
+                            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                        let byte = byte.ok_or_else(|| LexErrorKind::InvalidEscape(data[i..].chars().take(4).collect()))?;
+                        bytes.push(byte);
+                        chars.nth(1);
+                    },
+                    Some((_, c)) => return Err(LexErrorKind::InvalidEscape(format!("\\{}", c))),
+                    None => break
+                },
+                c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+            }
         }
+
+        Err(LexErrorKind::UnterminatedString)
     }
 
-    fn lex_not(data: String) -> (TokenKind, usize) {
-        if data.as_bytes()[1] as char == '=' {
-            (TokenKind::NotEquals, 2)
-        } else {
-            (TokenKind::Not, 1)
+    // Writes bytes as a byte string literal, escaping anything that isn't
+    // printable ASCII
+    pub fn escape_bytes(bytes: &[u8]) -> String {
+        let mut literal = String::from("b\"");
+
+        for &byte in bytes {
+            match byte {
+                b'\\' => literal.push_str("\\\\"),
+                b'"' => literal.push_str("\\\""),
+                b' '..=b'~' => literal.push(byte as char),
+                _ => literal.push_str(&format!("\\x{:02x}", byte))
+            }
         }
+
+        literal.push('"');
+        literal
+    }
+
+    // Operators and punctuation. The longest one the source starts with
+    // wins, so == is never read as two =, and adding an operator is adding
+    // a line here
+    const OPERATORS: [(&str, TokenKind); 17] = [
+        ("==", TokenKind::IsEquals),
+        ("!=", TokenKind::NotEquals),
+        (".", TokenKind::Dot),
+        ("=", TokenKind::Assign),
+        ("!", TokenKind::Not),
+        ("<", TokenKind::Less),
+        (">", TokenKind::Greater),
+        ("+", TokenKind::Plus),
+        ("-", TokenKind::Minus),
+        ("*", TokenKind::Asterisk),
+        ("/", TokenKind::ForwardSlash),
+        ("%", TokenKind::Percent),
+        ("(", TokenKind::Lparen),
+        (")", TokenKind::Rparen),
+        ("\n", TokenKind::NewLine),
+        (";", TokenKind::Semicolon),
+        (",", TokenKind::Comma)
+    ];
+
+    fn lex_operator(data: &str) -> Option<(TokenKind, usize)> {
+        OPERATORS.iter()
+            .filter(|(operator, _)| data.starts_with(operator))
+            .max_by_key(|(operator, _)| operator.len())
+            .map(|(operator, kind)| (kind.clone(), operator.len()))
     }
 
+    // Newlines separate statements, so they are tokens rather than whitespace
     fn skip_whitespace(data: &str) -> usize {
-        match take_while(data, |c| c.is_whitespace()) {
+        match take_while(data, |c| c.is_whitespace() && c != '\n') {
             Ok((_, bytes_skipped)) => bytes_skipped,
             _ => 0
         }
     }
 
+    // Returns the rest of `src` starting at `pattern`, or nothing if it never occurs
     fn skip_until<'a>(mut src: &'a str, pattern: &str) -> &'a str {
         while !src.is_empty() && !src.starts_with(pattern) {
             let next_char_size = src.chars().next().expect("String can't be empty").len_utf8();
             src = &src[next_char_size..];
         }
 
-        &src[pattern.len()..]
+        src
     }
 
     fn skip_comments(src: &str) -> usize {
-        let pairs = [("//", "\n"), ("/*", "*/")];
+        // Line comments leave their newline behind, since it ends the statement
+        let pairs = [("//", "\n", false), ("/*", "*/", true)];
 
-        for &(pattern, matcher) in &pairs {
+        for &(pattern, matcher, consume) in &pairs {
             if src.starts_with(pattern) {
-                let leftovers = skip_until(src, matcher);
+                let mut leftovers = skip_until(src, matcher);
+                if consume && !leftovers.is_empty() {
+                    leftovers = &leftovers[matcher.len()..];
+                }
                 return src.len() - leftovers.len();
             }
         }
@@ -220,56 +510,28 @@ pub mod lex {
         }
     }
 
-    fn lex_one(data: &str) -> Result<(TokenKind, usize), usize> {
+    fn lex_one(data: &str) -> Result<(TokenKind, usize), LexErrorKind> {
         let next = match data.chars().next() {
             Some(c) => c,
-            None => panic!("Unexpected EOF")
+            None => return Err(LexErrorKind::UnexpectedEof)
         };
 
+        if let Some(operator) = lex_operator(data) {
+            return Ok(operator);
+        }
+
         let (tok, length) = match next {
-            '.' => (TokenKind::Dot, 1),
-            '=' => lex_equals(data.to_string()),
-            '!' => lex_not(data.to_string()),
-            '<' => (TokenKind::Less, 1),
-            '>' => (TokenKind::Greater, 1),
-            '+' => (TokenKind::Plus, 1),
-            '-' => (TokenKind::Minus, 1),
-            '*' => (TokenKind::Asterisk, 1),
-            '/' => (TokenKind::ForwardSlash, 1),
-            '(' => (TokenKind::Lparen, 1),
-            ')' => (TokenKind::Rparen, 1),
-            '\n' => (TokenKind::NewLine, 1),
-            '"' => lex_string(data).expect("Couldn't lex a string"),
-            '0'..='9' => lex_number(data).expect("Couldn't lex a number"),
-            c @ '_' | c if c.is_alphabetic() => {
-                let ident = lex_ident(data).expect("Couldn't lex an identifier");
-
-                if let TokenKind::Identifier(i) = &ident.0 {
-                    match i.as_str() {
-                        "if" => (TokenKind::If, 2),
-                        "while" => (TokenKind::While, 5),
-                        "true" => (TokenKind::Boolean(true), 4),
-                        "false" => (TokenKind::Boolean(false), 5),
-                        _ => ident
-                    }
-                } else {
-                    ident
-                }
-            }
-            other => panic!("Unknown character '{}'", other)
+            '"' => lex_string(data)?,
+            'b' if data[1..].starts_with('"') => lex_bytes(data)?,
+            '0'..='9' => lex_number(data)?,
+            c @ '_' | c if c.is_alphabetic() => lex_ident(data)?,
+            other => return Err(LexErrorKind::UnknownCharacter(other))
         };
 
         Ok((tok, length))
     }
 
-    pub fn lex(src: &str) -> Result<Vec<TokenKind>, String> {
-        let mut lexer = Lexer::new(src);
-        let mut tokens = Vec::new();
-
-        while let Some(tok) = lexer.next_token().expect("Can not get next token!") {
-            tokens.push(tok.0);
-        }
-
-        Ok(tokens)
+    pub fn lex(src: &str) -> Result<Vec<Token>, LexError> {
+        Lexer::new(src).collect()
     }
 }
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use crate::json::Json;
+
+use super::{Node, Span, TokenKind};
+
+// Token kinds without a payload, by the name they have in JSON. In a tree,
+// ( only shows up as the node of a call
+const SIMPLE_KINDS: [(&str, TokenKind); 20] = [
+    ("Plus", TokenKind::Plus),
+    ("Minus", TokenKind::Minus),
+    ("Asterisk", TokenKind::Asterisk),
+    ("ForwardSlash", TokenKind::ForwardSlash),
+    ("Percent", TokenKind::Percent),
+    ("Dot", TokenKind::Dot),
+    ("Assign", TokenKind::Assign),
+    ("Call", TokenKind::Lparen),
+    ("Rparen", TokenKind::Rparen),
+    ("Semicolon", TokenKind::Semicolon),
+    ("Comma", TokenKind::Comma),
+    ("If", TokenKind::If),
+    ("While", TokenKind::While),
+    ("NewLine", TokenKind::NewLine),
+    ("Less", TokenKind::Less),
+    ("Greater", TokenKind::Greater),
+    ("IsEquals", TokenKind::IsEquals),
+    ("NotEquals", TokenKind::NotEquals),
+    ("Not", TokenKind::Not),
+    ("None", TokenKind::None)
+];
+
+// The JSON name of the kind, and its payload if it has one
+fn kind_to_json(kind: &TokenKind) -> (&'static str, Option<Json>) {
+    match kind {
+        TokenKind::Integer(n) => ("Integer", Some(Json::Int(*n))),
+        TokenKind::Decimal(n) => ("Decimal", Some(Json::Float(*n))),
+        // As digits, since JSON numbers can't be relied on past 64 bits
+        TokenKind::BigInt(n) => ("BigInt", Some(Json::Str(n.to_string()))),
+        TokenKind::Fixed(n) => ("Fixed", Some(Json::Str(n.to_string()))),
+        TokenKind::Identifier(name) => ("Identifier", Some(Json::Str(name.clone()))),
+        TokenKind::QuotedString(s) => ("QuotedString", Some(Json::Str(s.clone()))),
+        TokenKind::Bytes(bytes) => ("Bytes", Some(Json::Array(bytes.iter().map(|&b| Json::Int(b as i64)).collect()))),
+        TokenKind::Boolean(b) => ("Boolean", Some(Json::Bool(*b))),
+        other => {
+            let name = SIMPLE_KINDS.iter()
+                .find(|(_, k)| std::mem::discriminant(k) == std::mem::discriminant(other))
+                .map_or("Unknown", |(name, _)| name);
+            (name, None)
+        }
+    }
+}
+
+fn kind_from_json(name: &str, value: Option<&Json>) -> Result<TokenKind, String> {
+    let payload = |expected: &str| format!("{} needs a \"value\" of type {}", name, expected);
+
+    match name {
+        "Integer" => value.and_then(Json::as_i64).map(TokenKind::Integer).ok_or_else(|| payload("integer")),
+        "Decimal" => value.and_then(Json::as_f64).map(TokenKind::Decimal).ok_or_else(|| payload("number")),
+        "BigInt" => value.and_then(Json::as_str).and_then(|s| s.parse().ok()).map(|n| TokenKind::BigInt(Arc::new(n))).ok_or_else(|| payload("string of digits")),
+        "Fixed" => value.and_then(Json::as_str).and_then(|s| s.parse().ok()).map(|n| TokenKind::Fixed(Arc::new(n))).ok_or_else(|| payload("string of digits")),
+        "Identifier" => value.and_then(Json::as_str).map(|s| TokenKind::Identifier(s.to_string())).ok_or_else(|| payload("string")),
+        "QuotedString" => value.and_then(Json::as_str).map(|s| TokenKind::QuotedString(s.to_string())).ok_or_else(|| payload("string")),
+        "Bytes" => value.and_then(Json::as_array)
+            .and_then(|items| items.iter().map(|b| b.as_i64().and_then(|b| u8::try_from(b).ok())).collect::<Option<Vec<u8>>>())
+            .map(TokenKind::Bytes)
+            .ok_or_else(|| payload("array of bytes")),
+        "Boolean" => value.and_then(Json::as_bool).map(TokenKind::Boolean).ok_or_else(|| payload("boolean")),
+        other => SIMPLE_KINDS.iter()
+            .find(|(n, _)| *n == other)
+            .map(|(_, k)| k.clone())
+            .ok_or_else(|| format!("Unknown node kind {:?}", other))
+    }
+}
+
+fn span_to_json(span: Span) -> Json {
+    Json::object(vec![
+        ("line", span.line.into()),
+        ("col", span.col.into()),
+        ("len", span.len.into())
+    ])
+}
+
+// A missing span is fine, for trees made by hand
+fn span_from_json(json: Option<&Json>) -> Result<Span, String> {
+    let json = match json {
+        Some(json) => json,
+        None => return Ok(Span::default())
+    };
+
+    let field = |key: &str| json.get(key).and_then(Json::as_i64).map(|n| n as usize)
+        .ok_or_else(|| format!("Span needs an integer {:?}", key));
+
+    Ok(Span { line: field("line")?, col: field("col")?, len: field("len")? })
+}
+
+impl Node {
+    // An object like {"kind": "Plus", "span": {...}, "children": [...]},
+    // with a "value" for literals and identifiers
+    pub fn to_json(&self) -> Json {
+        let (kind, value) = kind_to_json(&self.entry);
+
+        let mut fields = vec![("kind", Json::from(kind))];
+        if let Some(value) = value {
+            fields.push(("value", value));
+        }
+        fields.push(("span", span_to_json(self.span)));
+        fields.push(("children", Json::Array(self.children.iter().map(Node::to_json).collect())));
+
+        Json::object(fields)
+    }
+
+    pub fn from_json(json: &Json) -> Result<Node, String> {
+        let kind = json.get("kind").and_then(Json::as_str).ok_or("Node needs a string \"kind\"")?;
+
+        let children = match json.get("children") {
+            Some(children) => children.as_array().ok_or("\"children\" should be an array")?
+                .iter()
+                .map(Node::from_json)
+                .collect::<Result<Vec<Node>, String>>()?,
+            None => Vec::new()
+        };
+
+        Ok(Node {
+            children,
+            entry: kind_from_json(kind, json.get("value"))?,
+            span: span_from_json(json.get("span"))?,
+            symbol: None
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use super::{Node, TokenKind};
+
+// Walks a tree of nodes. Every method has a default that goes on into the
+// children, so a visitor only overrides the kinds of node it cares about and
+// calls `walk` (or not) to decide whether to look further down
+pub trait NodeVisitor {
+    fn visit(&mut self, node: &Node) {
+        match &node.entry {
+            TokenKind::Assign if node.children.len() == 2 => self.visit_assign(node, &node.children[0], &node.children[1]),
+            TokenKind::Lparen if !node.children.is_empty() => self.visit_call(node, &node.children[0], &node.children[1..]),
+            TokenKind::Identifier(name) => self.visit_identifier(node, name),
+            _ if node.children.is_empty() => self.visit_literal(node),
+            _ => self.visit_operator(node)
+        }
+    }
+
+    // `target = value`. By default the value is visited before the target,
+    // in the order they are evaluated
+    fn visit_assign(&mut self, _node: &Node, target: &Node, value: &Node) {
+        self.visit(value);
+        self.visit(target);
+    }
+
+    // `callee(args)`. By default only the arguments are visited, as the
+    // callee names a function and not a variable
+    fn visit_call(&mut self, _node: &Node, _callee: &Node, args: &[Node]) {
+        for arg in args {
+            self.visit(arg);
+        }
+    }
+
+    fn visit_identifier(&mut self, _node: &Node, _name: &str) {}
+
+    fn visit_literal(&mut self, _node: &Node) {}
+
+    // A binary operator, or the `0 - x` a unary one is parsed into
+    fn visit_operator(&mut self, node: &Node) {
+        walk(self, node);
+    }
+}
+
+// Visits every child of the node in order
+pub fn walk<V: NodeVisitor + ?Sized>(visitor: &mut V, node: &Node) {
+    for child in &node.children {
+        visitor.visit(child);
+    }
+}
+
+// Rewrites a tree of nodes in place. By default the children are folded
+// before their parent, so `fold_node` sees operands that are already done
+pub trait NodeFolder {
+    fn fold(&mut self, node: &mut Node) {
+        walk_mut(self, node);
+        self.fold_node(node);
+    }
+
+    fn fold_node(&mut self, _node: &mut Node) {}
+}
+
+// Folds every child of the node in order
+pub fn walk_mut<F: NodeFolder + ?Sized>(folder: &mut F, node: &mut Node) {
+    for child in &mut node.children {
+        folder.fold(child);
+    }
+}
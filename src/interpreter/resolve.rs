@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use super::parser::{Node, NodeFolder, NodeVisitor, TokenKind};
+use super::{closest_name, Error, RuntimeError, RuntimeErrorKind, State, Symbol};
+
+// Stores the symbol of every variable in its node. Callees name functions,
+// not variables, so they are left alone
+struct Binder<'a> {
+    state: &'a mut State
+}
+
+impl NodeFolder for Binder<'_> {
+    fn fold(&mut self, node: &mut Node) {
+        let callee = usize::from(matches!(node.entry, TokenKind::Lparen));
+
+        for child in node.children.iter_mut().skip(callee) {
+            self.fold(child);
+        }
+
+        if let TokenKind::Identifier(name) = &node.entry {
+            node.symbol = Some(self.state.symbols.intern(name));
+        }
+    }
+}
+
+// Checks every variable read against the variables of the state and those
+// assigned earlier in the source, before anything runs
+struct Resolver<'a> {
+    state: &'a mut State,
+    assigned: HashSet<Symbol>,
+    error: Option<RuntimeError>
+}
+
+impl NodeVisitor for Resolver<'_> {
+    fn visit_assign(&mut self, _node: &Node, target: &Node, value: &Node) {
+        self.visit(value);
+
+        if let Some(symbol) = target.symbol {
+            self.assigned.insert(symbol);
+        } else {
+            self.visit(target);
+        }
+    }
+
+    fn visit_identifier(&mut self, node: &Node, name: &str) {
+        let Some(symbol) = node.symbol else { return };
+
+        if self.error.is_none() && !self.assigned.contains(&symbol) && self.state.slot(symbol).is_none() {
+            let kind = RuntimeErrorKind::UndefinedVariable(name.to_string(), closest_name(name, self.state));
+            self.error = Some(RuntimeError { kind, span: node.span });
+        }
+    }
+}
+
+// Interns every identifier in the statements and stores its symbol in the
+// node, so evaluation indexes the slot directly, and reports the first variable read before anything
+// assigns it. As there is no control flow, evaluation order is source order
+// and a variable that isn't defined here would fail at runtime anyway, only
+// after the statements before it already ran
+pub fn resolve(statements: &mut [Node], state: &mut State) -> Result<(), Error> {
+    let mut binder = Binder { state };
+    for statement in statements.iter_mut() {
+        binder.fold(statement);
+    }
+
+    let mut resolver = Resolver { state, assigned: HashSet::new(), error: None };
+
+    for statement in statements {
+        resolver.visit(statement);
+    }
+
+    match resolver.error {
+        Some(e) => Err(Error::Runtime(e)),
+        None => Ok(())
+    }
+}
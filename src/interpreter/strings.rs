@@ -0,0 +1,103 @@
+// Builtins for strings, which work on Unicode scalar values rather than
+// UTF-8 bytes, so "é" has a length of 1 and reverses to itself. `len` and
+// `slice` take bytes as well, counting bytes there. Offsets below zero
+// count from the end
+use super::bytes::{arity, bytes, index, offset, string};
+use super::{Error, State, ValueKind};
+
+// The positions a slice takes from something `len` long, given its start,
+// stop and optional step. A negative step walks backwards, so by default
+// from the last element down to the first
+fn positions(bounds: &[ValueKind], len: usize) -> Result<impl Iterator<Item = usize>, Error> {
+    let step = match bounds.get(2) {
+        None | Some(ValueKind::None) => 1,
+        Some(ValueKind::Integer(0)) => return Err(Error::Io(String::from("The step of a slice can't be zero"))),
+        Some(ValueKind::Integer(n)) => *n,
+        Some(other) => return Err(Error::Io(format!("Expected an integer, but got {}", other.type_name())))
+    };
+
+    let bound = |value: &ValueKind| match value {
+        ValueKind::None => Ok(None),
+        value => offset(value, len).map(Some)
+    };
+    let (start, stop) = (bound(&bounds[0])?, bound(&bounds[1])?);
+
+    // Positions are walked as i64, so going backwards can stop before 0
+    let (start, stop) = if step > 0 {
+        (start.unwrap_or(0) as i64, stop.unwrap_or(len) as i64)
+    } else {
+        let start = match start {
+            Some(start) if start == len && len > 0 => return Err(Error::Io(format!("{} is out of range for a length of {}", start, len))),
+            Some(start) => start as i64,
+            None => len as i64 - 1
+        };
+        (start, stop.map_or(-1, |stop| stop as i64))
+    };
+
+    if (step > 0 && start > stop) || (step < 0 && start < stop) {
+        return Err(Error::Io(format!("A slice from {} to {} can't have a step of {}", start, stop, step)));
+    }
+
+    let count = (stop - start).unsigned_abs().div_ceil(step.unsigned_abs());
+    Ok((0..count).map(move |k| (start + k as i64 * step) as usize))
+}
+
+pub fn register(state: &mut State) {
+    state.register_fn("len", |args| {
+        arity(args, &[1])?;
+        let len = match &args[0] {
+            ValueKind::Str(s) => s.chars().count(),
+            other => bytes(other)?.len()
+        };
+        Ok(ValueKind::Integer(len as i64))
+    });
+
+    // The character at an offset, as a string of one character
+    state.register_fn("char_at", |args| {
+        arity(args, &[2])?;
+        let s = string(&args[0])?;
+        let i = index(&args[1], s.chars().count())?;
+        Ok(ValueKind::from(s.chars().nth(i).unwrap_or_default().to_string()))
+    });
+
+    // slice(a, start, stop) or slice(a, start, stop, step): the characters
+    // or bytes from `start` up to but not including `stop`, taking every
+    // `step`th one. Either bound can be none for the whole way
+    state.register_fn("slice", |args| {
+        arity(args, &[3, 4])?;
+        let len = match &args[0] {
+            ValueKind::Str(s) => s.chars().count(),
+            other => bytes(other)?.len()
+        };
+        let positions = positions(&args[1..], len)?;
+
+        match &args[0] {
+            ValueKind::Str(s) => {
+                let chars: Vec<char> = s.chars().collect();
+                Ok(ValueKind::from(positions.map(|i| chars[i]).collect::<String>()))
+            },
+            other => {
+                let bytes = bytes(other)?;
+                Ok(ValueKind::from(positions.map(|i| bytes[i]).collect::<Vec<u8>>()))
+            }
+        }
+    });
+
+    // Case mapping can change the length, like "ß" becoming "SS"
+    state.register_fn("upper", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(string(&args[0])?.to_uppercase()))
+    });
+
+    state.register_fn("lower", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(string(&args[0])?.to_lowercase()))
+    });
+
+    // Reverses the characters, so combining marks end up before their base
+    // character rather than breaking a UTF-8 sequence
+    state.register_fn("reverse", |args| {
+        arity(args, &[1])?;
+        Ok(ValueKind::from(string(&args[0])?.chars().rev().collect::<String>()))
+    });
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+// The hash of FxHash, as used in rustc. Much faster than the default SipHash
+// on short keys like variable names, but not resistant to collisions
+// crafted on purpose, which doesn't matter for names in a script
+#[derive(Default)]
+pub struct FxHasher {
+    hash: u64
+}
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+
+        for chunk in &mut chunks {
+            self.add(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+
+        for byte in chunks.remainder() {
+            self.add(*byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.add(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.add(n);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(feature = "fxhash")]
+type BuildNameHasher = std::hash::BuildHasherDefault<FxHasher>;
+#[cfg(not(feature = "fxhash"))]
+type BuildNameHasher = std::collections::hash_map::RandomState;
+
+// An interned name. Two symbols from the same interner are equal exactly
+// when their names are, so comparing and hashing them is cheap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    // Symbols are handed out counting up from 0, so they can index a table
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// Hands out one symbol per distinct name, and keeps the names around to turn
+// symbols back into text
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<Arc<str>, Symbol, BuildNameHasher>,
+    names: Vec<Arc<str>>
+}
+
+impl Interner {
+    // Room for `capacity` names before growing
+    pub fn with_capacity(capacity: usize) -> Interner {
+        Interner {
+            ids: HashMap::with_capacity_and_hasher(capacity, BuildNameHasher::default()),
+            names: Vec::with_capacity(capacity)
+        }
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.ids.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.names.len() as u32);
+        let name: Arc<str> = Arc::from(name);
+        self.names.push(name.clone());
+        self.ids.insert(name, symbol);
+        symbol
+    }
+
+    // The symbol of a name seen before, without interning it
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+
+    // Every symbol handed out with its name, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        self.names.iter().enumerate().map(|(i, name)| (Symbol(i as u32), &**name))
+    }
+}
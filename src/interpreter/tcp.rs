@@ -0,0 +1,164 @@
+// Builtins for speaking line-based protocols over TCP. Scripts can only
+// hold plain values, so connections are numbered handles into a table the
+// builtins of one state share. Everything here needs the network capability.
+// Connecting and receiving give up after a while, and a waiting receive
+// wakes up regularly so cancelling the evaluation interrupts it
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{text, CancelToken, Capabilities, Error, RuntimeError, RuntimeErrorKind, State, ValueKind};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// How long sending or waiting for a line may take
+const TIMEOUT: Duration = Duration::from_secs(30);
+// How often a waiting receive checks whether it was cancelled
+const POLL: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+struct Connections {
+    open: HashMap<i64, BufReader<TcpStream>>,
+    next: i64
+}
+
+fn io_error(what: &str, e: std::io::Error) -> Error {
+    Error::Io(format!("Can't {}: {}", what, e))
+}
+
+fn expect_args(args: &[ValueKind], count: usize) -> Result<(), Error> {
+    if args.len() == count {
+        Ok(())
+    } else {
+        Err(Error::Io(format!("Expected {} arguments, but got {}", count, args.len())))
+    }
+}
+
+fn handle(value: &ValueKind) -> Result<i64, Error> {
+    match value {
+        ValueKind::Integer(n) => Ok(*n),
+        other => Err(Error::Io(format!("{} is not a connection", other)))
+    }
+}
+
+fn not_open(handle: i64) -> Error {
+    Error::Io(format!("No open connection {}", handle))
+}
+
+fn connect(host: &str, port: u16) -> std::io::Result<TcpStream> {
+    let mut last = None;
+
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(POLL))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(stream);
+            },
+            Err(e) => last = Some(e)
+        }
+    }
+
+    Err(last.unwrap_or_else(|| std::io::Error::new(ErrorKind::NotFound, "no address found")))
+}
+
+fn interrupted() -> Error {
+    Error::Runtime(RuntimeError { kind: RuntimeErrorKind::Interrupted, span: Default::default() })
+}
+
+impl Connections {
+    fn get(&mut self, value: &ValueKind) -> Result<&mut BufReader<TcpStream>, Error> {
+        let handle = handle(value)?;
+        self.open.get_mut(&handle).ok_or_else(|| not_open(handle))
+    }
+}
+
+// Reads up to the next line ending, or None at the end of the stream.
+// Bytes read before a timeout stay in `bytes`, so waiting again is safe
+fn receive(stream: &mut BufReader<TcpStream>, cancel: &CancelToken) -> Result<Option<String>, Error> {
+    let started = Instant::now();
+    let mut bytes = Vec::new();
+
+    loop {
+        match stream.read_until(b'\n', &mut bytes) {
+            Ok(0) if bytes.is_empty() => return Ok(None),
+            Ok(_) => break,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {
+                if cancel.is_cancelled() {
+                    return Err(interrupted());
+                }
+                if started.elapsed() > TIMEOUT {
+                    return Err(io_error("receive", std::io::Error::new(ErrorKind::TimedOut, "no line in time")));
+                }
+            },
+            Err(e) => return Err(io_error("receive", e))
+        }
+    }
+
+    String::from_utf8(bytes).map(Some).map_err(|_| Error::Io("Can't receive: the line isn't UTF-8".to_string()))
+}
+
+pub fn register(state: &mut State) {
+    let connections = Arc::new(Mutex::new(Connections::default()));
+
+    // Connects to host:port and returns the handle of the connection
+    let table = connections.clone();
+    state.register_fn_requiring("tcp_connect", Capabilities::NETWORK, move |args| {
+        expect_args(args, 2)?;
+
+        let (ValueKind::Str(host), ValueKind::Integer(port)) = (&args[0], &args[1]) else {
+            return Err(Error::Io("Expected a host and a port".to_string()));
+        };
+        let port = u16::try_from(*port).map_err(|_| Error::Io(format!("{} is not a port", port)))?;
+        let stream = connect(host, port).map_err(|e| io_error(&format!("connect to {}:{}", host, port), e))?;
+
+        let mut table = table.lock().unwrap();
+        table.next += 1;
+        let handle = table.next;
+        table.open.insert(handle, BufReader::new(stream));
+        Ok(ValueKind::Integer(handle))
+    });
+
+    // Sends the value as a line
+    let table = connections.clone();
+    state.register_fn_requiring("tcp_send", Capabilities::NETWORK, move |args| {
+        expect_args(args, 2)?;
+
+        let mut table = table.lock().unwrap();
+        let stream = table.get(&args[0])?.get_mut();
+        writeln!(stream, "{}", text(&args[1])).map_err(|e| io_error("send", e))?;
+        Ok(ValueKind::None)
+    });
+
+    // The next line received without its line ending, or none once the
+    // other side closed the connection
+    let table = connections.clone();
+    let cancel = state.cancel.clone();
+    state.register_fn_requiring("tcp_recv", Capabilities::NETWORK, move |args| {
+        expect_args(args, 1)?;
+
+        // Taken out of the table while waiting, so the lock isn't held
+        let handle = handle(&args[0])?;
+        let mut stream = table.lock().unwrap().open.remove(&handle).ok_or_else(|| not_open(handle))?;
+        let received = receive(&mut stream, &cancel);
+        table.lock().unwrap().open.insert(handle, stream);
+
+        match received? {
+            Some(mut line) => {
+                let end = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(end);
+                Ok(ValueKind::from(line))
+            },
+            None => Ok(ValueKind::None)
+        }
+    });
+
+    state.register_fn_requiring("tcp_close", Capabilities::NETWORK, move |args| {
+        expect_args(args, 1)?;
+
+        let handle = handle(&args[0])?;
+        connections.lock().unwrap().open.remove(&handle).ok_or_else(|| not_open(handle))?;
+        Ok(ValueKind::None)
+    });
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use super::parser::{Node, TokenKind};
+use super::{RuntimeError, RuntimeErrorKind, Side, TypeError, ValueKind};
+
+// What is known about the value of an expression before it runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Integer,
+    Decimal,
+    Fixed,
+    // Any kind of number, like the result of dividing two integers, which
+    // is an integer or a rational
+    Number,
+    Str,
+    Bytes,
+    Boolean,
+    None,
+    // Anything, like the result of a host function
+    Unknown
+}
+
+impl Type {
+    pub fn name(self) -> &'static str {
+        match self {
+            Type::Integer => "integer",
+            Type::Decimal => "decimal",
+            Type::Fixed => "exact decimal",
+            Type::Number => "number",
+            Type::Str => "string",
+            Type::Bytes => "bytes",
+            Type::Boolean => "boolean",
+            Type::None => "none",
+            Type::Unknown => "unknown"
+        }
+    }
+
+    // Arithmetic only works on numbers, so these fail whatever the operator
+    fn is_never_number(self) -> bool {
+        matches!(self, Type::Str | Type::Bytes | Type::Boolean | Type::None)
+    }
+}
+
+// The type of a value that is already known, like a variable of a session
+pub fn type_of(value: &ValueKind) -> Type {
+    match value {
+        ValueKind::Integer(_) | ValueKind::BigInt(_) => Type::Integer,
+        ValueKind::Decimal(_) => Type::Decimal,
+        ValueKind::Fixed(_) => Type::Fixed,
+        ValueKind::Rational(..) => Type::Number,
+        ValueKind::Str(_) => Type::Str,
+        ValueKind::Bytes(_) => Type::Bytes,
+        ValueKind::Boolean(_) => Type::Boolean,
+        ValueKind::None => Type::None,
+        ValueKind::Identifier(_) => Type::Unknown
+    }
+}
+
+// What the standard builtins return. A host can replace them, but then it
+// would be confusing to keep the name
+fn builtin_type(name: &str) -> Type {
+    match name {
+        "str" | "lower" | "upper" | "char_at" | "from_bytes" | "hex_encode" | "base64_encode" => Type::Str,
+        "to_bytes" | "hex_decode" | "base64_decode" | "read_bytes" => Type::Bytes,
+        "len" | "byte_at" => Type::Integer,
+        "to_float" => Type::Decimal,
+        _ => Type::Unknown
+    }
+}
+
+// Follows the type of every variable through the statements in order
+#[derive(Default)]
+struct Checker {
+    variables: HashMap<String, Type>,
+    errors: Vec<RuntimeError>
+}
+
+impl Checker {
+    fn infer(&mut self, node: &Node) -> Type {
+        match &node.entry {
+            TokenKind::Integer(_) | TokenKind::BigInt(_) if node.children.is_empty() => Type::Integer,
+            TokenKind::Decimal(_) if node.children.is_empty() => Type::Decimal,
+            TokenKind::Fixed(_) if node.children.is_empty() => Type::Fixed,
+            TokenKind::QuotedString(_) if node.children.is_empty() => Type::Str,
+            TokenKind::Bytes(_) if node.children.is_empty() => Type::Bytes,
+            TokenKind::Boolean(_) if node.children.is_empty() => Type::Boolean,
+            TokenKind::None if node.children.is_empty() => Type::None,
+            // Variables the host defined are unknown here
+            TokenKind::Identifier(name) => self.variables.get(name).copied().unwrap_or(Type::Unknown),
+            TokenKind::Lparen => {
+                for arg in node.children.iter().skip(1) {
+                    self.infer(arg);
+                }
+
+                match node.children.first().map(|callee| &callee.entry) {
+                    Some(TokenKind::Identifier(name)) => builtin_type(name),
+                    _ => Type::Unknown
+                }
+            },
+            TokenKind::Assign if node.children.len() == 2 => {
+                let value = self.infer(&node.children[1]);
+
+                if let TokenKind::Identifier(name) = &node.children[0].entry {
+                    self.variables.insert(name.clone(), value);
+                }
+                value
+            },
+            op if node.children.len() == 2 => {
+                let lhs = self.infer(&node.children[0]);
+                let rhs = self.infer(&node.children[1]);
+                self.operation(node, op, lhs, rhs)
+            },
+            _ => Type::Unknown
+        }
+    }
+
+    // The same rules as evaluation, on types instead of values
+    fn operation(&mut self, node: &Node, op: &TokenKind, lhs: Type, rhs: Type) -> Type {
+        // Any values can be compared. Ordering different kinds only fails
+        // at runtime
+        if let TokenKind::IsEquals | TokenKind::NotEquals | TokenKind::Less | TokenKind::Greater = op {
+            return Type::Boolean;
+        }
+
+        // A template string formats any value
+        if let TokenKind::Percent = op {
+            if !matches!(lhs, Type::Str | Type::Unknown) {
+                let kind = RuntimeErrorKind::Type(TypeError { op: op.clone(), side: Side::Left, expected: "string", found: lhs.name() });
+                self.errors.push(RuntimeError { kind, span: node.children[0].span });
+            }
+            return Type::Str;
+        }
+
+        for (side, operand, found) in [(Side::Left, &node.children[0], lhs), (Side::Right, &node.children[1], rhs)] {
+            if found.is_never_number() {
+                let kind = RuntimeErrorKind::Type(TypeError {
+                    op: op.clone(),
+                    side,
+                    expected: "integer or decimal",
+                    found: found.name()
+                });
+                self.errors.push(RuntimeError { kind, span: operand.span });
+                return Type::Unknown;
+            }
+        }
+
+        match (op, lhs, rhs) {
+            (_, Type::Unknown, _) | (_, _, Type::Unknown) => Type::Number,
+            (_, Type::Decimal, _) | (_, _, Type::Decimal) => Type::Decimal,
+            (_, Type::Fixed, _) | (_, _, Type::Fixed) => Type::Fixed,
+            (TokenKind::Plus | TokenKind::Minus | TokenKind::Asterisk, Type::Integer, Type::Integer) => Type::Integer,
+            _ => Type::Number
+        }
+    }
+}
+
+// Infers the type of every expression without running anything, reporting
+// operands that are sure to have the wrong type as the runtime error they
+// would cause. The left operand is checked first, like at runtime, and
+// each operator is only reported once
+pub fn check_types(statements: &[Node]) -> Vec<RuntimeError> {
+    let mut checker = Checker::default();
+
+    for statement in statements {
+        checker.infer(statement);
+    }
+
+    checker.errors
+}
+
+// The type of what the statements end with, without running them, given
+// the types of variables that already exist. Fails with the errors the
+// statements are sure to cause
+pub fn infer(statements: &[Node], variables: HashMap<String, Type>) -> Result<Type, Vec<RuntimeError>> {
+    let mut checker = Checker { variables, errors: Vec::new() };
+    let mut last = Type::None;
+
+    for statement in statements {
+        last = checker.infer(statement);
+    }
+
+    if checker.errors.is_empty() {
+        Ok(last)
+    } else {
+        Err(checker.errors)
+    }
+}
+
+// The type of every variable once the statements have run
+pub fn variable_types(statements: &[Node]) -> HashMap<String, Type> {
+    let mut checker = Checker::default();
+
+    for statement in statements {
+        checker.infer(statement);
+    }
+
+    checker.variables
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// Just enough JSON to read and write machine-readable data, without pulling
+// in a dependency. Objects keep their keys in insertion order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    // The value of `key` if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::Str(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Int(n) => Some(*n),
+            _ => None
+        }
+    }
+
+    // Integers count as numbers too
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Float(n) => Some(*n),
+            Json::Int(n) => Some(*n as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None
+        }
+    }
+
+    // Reads a single JSON value, surrounded by nothing but whitespace
+    pub fn parse(src: &str) -> Result<Json, String> {
+        let mut chars = src.chars().peekable();
+        let value = parse_value(&mut chars)?;
+
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected '{}' after the JSON value", c))
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(other: &str) -> Json {
+        Json::Str(other.to_string())
+    }
+}
+
+impl From<usize> for Json {
+    fn from(other: usize) -> Json {
+        Json::Int(other as i64)
+    }
+}
+
+type Input<'a> = Peekable<Chars<'a>>;
+
+fn skip_whitespace(chars: &mut Input) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Input, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("Expected '{}' but found '{}'", expected, c)),
+        None => Err(format!("Expected '{}' but the input ended", expected))
+    }
+}
+
+fn expect_word(chars: &mut Input, word: &str, value: Json) -> Result<Json, String> {
+    for c in word.chars() {
+        expect(chars, c)?;
+    }
+
+    Ok(value)
+}
+
+fn parse_value(chars: &mut Input) -> Result<Json, String> {
+    skip_whitespace(chars);
+
+    match chars.peek() {
+        Some('n') => expect_word(chars, "null", Json::Null),
+        Some('t') => expect_word(chars, "true", Json::Bool(true)),
+        Some('f') => expect_word(chars, "false", Json::Bool(false)),
+        Some('"') => parse_string(chars).map(Json::Str),
+        Some('[') => parse_array(chars),
+        Some('{') => parse_object(chars),
+        Some(c) if *c == '-' || c.is_ascii_digit() => parse_number(chars),
+        Some(c) => Err(format!("Unexpected '{}'", c)),
+        None => Err(String::from("Expected a value but the input ended"))
+    }
+}
+
+fn parse_number(chars: &mut Input) -> Result<Json, String> {
+    let mut number = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() || "+-.eE".contains(c) {
+            number.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+
+    if number.contains(['.', 'e', 'E']) {
+        number.parse().map(Json::Float).map_err(|_| format!("Invalid number {}", number))
+    } else {
+        number.parse().map(Json::Int).map_err(|_| format!("Invalid number {}", number))
+    }
+}
+
+fn parse_string(chars: &mut Input) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut s = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                        .ok_or_else(|| format!("Invalid escape \\u{}", hex))?;
+                    s.push(c);
+                },
+                Some(c) => s.push(c),
+                None => return Err(String::from("Unterminated string"))
+            },
+            Some(c) => s.push(c),
+            None => return Err(String::from("Unterminated string"))
+        }
+    }
+}
+
+// Calls `item` for every element up to `close`, with commas in between
+fn parse_sequence<F>(chars: &mut Input, close: char, mut item: F) -> Result<(), String> where F: FnMut(&mut Input) -> Result<(), String> {
+    skip_whitespace(chars);
+    if chars.peek() == Some(&close) {
+        chars.next();
+        return Ok(());
+    }
+
+    loop {
+        item(chars)?;
+        skip_whitespace(chars);
+
+        match chars.next() {
+            Some(',') => continue,
+            Some(c) if c == close => return Ok(()),
+            Some(c) => return Err(format!("Expected ',' or '{}' but found '{}'", close, c)),
+            None => return Err(format!("Expected '{}' but the input ended", close))
+        }
+    }
+}
+
+fn parse_array(chars: &mut Input) -> Result<Json, String> {
+    expect(chars, '[')?;
+    let mut items = Vec::new();
+
+    parse_sequence(chars, ']', |chars| {
+        items.push(parse_value(chars)?);
+        Ok(())
+    })?;
+
+    Ok(Json::Array(items))
+}
+
+fn parse_object(chars: &mut Input) -> Result<Json, String> {
+    expect(chars, '{')?;
+    let mut fields = Vec::new();
+
+    parse_sequence(chars, '}', |chars| {
+        skip_whitespace(chars);
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        expect(chars, ':')?;
+        fields.push((key, parse_value(chars)?));
+        Ok(())
+    })?;
+
+    Ok(Json::Object(fields))
+}
+
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?
+        }
+    }
+
+    write!(f, "\"")
+}
+
+// Always written on a single line, so a stream of values is valid JSON Lines
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(n) => write!(f, "{}", n),
+            // Debug keeps the fraction of whole numbers, so they read back as floats
+            Json::Float(n) if n.is_finite() => write!(f, "{:?}", n),
+            Json::Float(_) => write!(f, "null"),
+            Json::Str(s) => write_str(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_str(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
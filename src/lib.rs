@@ -0,0 +1,17 @@
+// The term interpreter as a library, for embedding it into other programs.
+//
+// The items re-exported here are the stable API: the Interpreter engine,
+// values, the interpreter state, the errors, and the entry points that run
+// source code. Everything else can change between versions
+pub mod interpreter;
+pub mod engine;
+pub mod diagnostics;
+pub mod color;
+pub mod json;
+pub mod plugin;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+pub use interpreter::parser;
+pub use engine::{Interpreter, InterpreterBuilder, Value};
+pub use interpreter::{check, interpret, ConversionError, Error, State, ValueKind, Warning};
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use term::diagnostics::{Diagnostic, Severity};
+use term::interpreter::parser::{self, NodeVisitor, TokenKind};
+use term::interpreter::{self, types, Error, Node, Span};
+use term::json::Json;
+
+// A language server speaking JSON-RPC over stdin and stdout. Documents are
+// synced in full on every change, and positions count characters rather
+// than UTF-16 code units, which only differs outside the BMP
+pub fn serve() {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut documents: HashMap<String, String> = HashMap::new();
+
+    while let Some(message) = read_message(&mut input) {
+        let id = message.get("id").cloned();
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        let uri = params.get("textDocument").and_then(|d| d.get("uri")).and_then(Json::as_str).unwrap_or("").to_string();
+
+        match message.get("method").and_then(Json::as_str).unwrap_or("") {
+            "initialize" => respond(id, Json::object(vec![
+                ("capabilities", Json::object(vec![
+                    // Full document sync
+                    ("textDocumentSync", Json::Int(1)),
+                    ("hoverProvider", Json::Bool(true)),
+                    ("definitionProvider", Json::Bool(true))
+                ])),
+                ("serverInfo", Json::object(vec![("name", "term".into())]))
+            ])),
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").and_then(|d| d.get("text")).and_then(Json::as_str).unwrap_or("");
+                documents.insert(uri.clone(), text.to_string());
+                publish_diagnostics(&uri, text);
+            },
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Json::as_array).unwrap_or(&[]);
+
+                if let Some(text) = changes.last().and_then(|c| c.get("text")).and_then(Json::as_str) {
+                    documents.insert(uri.clone(), text.to_string());
+                    publish_diagnostics(&uri, text);
+                }
+            },
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                notify("textDocument/publishDiagnostics", Json::object(vec![
+                    ("uri", uri.as_str().into()),
+                    ("diagnostics", Json::Array(Vec::new()))
+                ]));
+            },
+            "textDocument/hover" => {
+                let result = documents.get(&uri)
+                    .and_then(|src| hover(src, position(&params)))
+                    .unwrap_or(Json::Null);
+                respond(id, result);
+            },
+            "textDocument/definition" => {
+                let result = documents.get(&uri)
+                    .and_then(|src| definition(src, position(&params)))
+                    .map_or(Json::Null, |span| Json::object(vec![("uri", uri.as_str().into()), ("range", range(span))]));
+                respond(id, result);
+            },
+            "shutdown" => respond(id, Json::Null),
+            "exit" => break,
+            // Requests have to be answered, notifications can be ignored
+            method if id.is_some() => respond_error(id, -32601, &format!("Unknown method {}", method)),
+            _ => {}
+        }
+    }
+}
+
+// Reads one message framed by a Content-Length header, or None at the end
+// of the input
+fn read_message(input: &mut impl BufRead) -> Option<Json> {
+    loop {
+        let mut length = None;
+
+        loop {
+            let mut header = String::new();
+            if input.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("Content-Length") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+
+        let mut body = vec![0; length?];
+        input.read_exact(&mut body).ok()?;
+
+        // A message that isn't JSON is skipped
+        if let Ok(message) = Json::parse(&String::from_utf8_lossy(&body)) {
+            return Some(message);
+        }
+    }
+}
+
+fn send(message: Json) {
+    let body = message.to_string();
+    let mut stdout = io::stdout();
+    write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    stdout.flush().unwrap();
+}
+
+fn respond(id: Option<Json>, result: Json) {
+    send(Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.unwrap_or(Json::Null)),
+        ("result", result)
+    ]));
+}
+
+fn respond_error(id: Option<Json>, code: i64, message: &str) {
+    send(Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.unwrap_or(Json::Null)),
+        ("error", Json::object(vec![("code", Json::Int(code)), ("message", message.into())]))
+    ]));
+}
+
+fn notify(method: &str, params: Json) {
+    send(Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params)
+    ]));
+}
+
+// The 1-based line and column the request is about
+fn position(params: &Json) -> (usize, usize) {
+    let position = params.get("position");
+    let line = position.and_then(|p| p.get("line")).and_then(Json::as_i64).unwrap_or(0);
+    let character = position.and_then(|p| p.get("character")).and_then(Json::as_i64).unwrap_or(0);
+    (line as usize + 1, character as usize + 1)
+}
+
+// LSP ranges are 0-based
+fn range(span: Span) -> Json {
+    let line = span.line.saturating_sub(1);
+    let col = span.col.saturating_sub(1);
+
+    Json::object(vec![
+        ("start", Json::object(vec![("line", line.into()), ("character", col.into())])),
+        ("end", Json::object(vec![("line", line.into()), ("character", (col + span.len).into())]))
+    ])
+}
+
+// Syntax errors, or the warnings and type errors once the source parses
+fn publish_diagnostics(uri: &str, src: &str) {
+    let diagnostics = match interpreter::check(src) {
+        Ok(warnings) => {
+            let statements = interpreter::parse(src).unwrap_or_default();
+            let mut diagnostics: Vec<Diagnostic> = warnings.iter().map(Diagnostic::from_warning).collect();
+
+            for e in types::check_types(&statements) {
+                diagnostics.extend(Diagnostic::from_error(&Error::Runtime(e)));
+            }
+            diagnostics
+        },
+        Err(e) => Diagnostic::from_error(&e)
+    };
+
+    let diagnostics = diagnostics.iter().map(|d| Json::object(vec![
+        ("range", range(d.span)),
+        // 1 is an error and 2 a warning
+        ("severity", Json::Int(if d.severity == Severity::Error { 1 } else { 2 })),
+        ("code", d.code.into()),
+        ("source", "term".into()),
+        ("message", d.message.as_str().into())
+    ])).collect();
+
+    notify("textDocument/publishDiagnostics", Json::object(vec![
+        ("uri", uri.into()),
+        ("diagnostics", Json::Array(diagnostics))
+    ]));
+}
+
+// The statements that parse, even when others have errors
+fn statements(src: &str) -> Option<Vec<Node>> {
+    parser::lex(src).ok().map(|tokens| parser::parse_program(&tokens).0)
+}
+
+// Finds the variable under a position, and where each variable is first
+// assigned
+#[derive(Default)]
+struct Names {
+    position: (usize, usize),
+    found: Option<(String, Span)>,
+    definitions: HashMap<String, Span>
+}
+
+impl NodeVisitor for Names {
+    fn visit_assign(&mut self, _node: &Node, target: &Node, value: &Node) {
+        self.visit(value);
+        self.visit(target);
+
+        if let TokenKind::Identifier(name) = &target.entry {
+            self.definitions.entry(name.clone()).or_insert(target.span);
+        }
+    }
+
+    fn visit_identifier(&mut self, node: &Node, name: &str) {
+        let (line, col) = self.position;
+
+        if node.span.line == line && (node.span.col..=node.span.col + node.span.len).contains(&col) {
+            self.found = Some((name.to_string(), node.span));
+        }
+    }
+}
+
+fn names(statements: &[Node], position: (usize, usize)) -> Names {
+    let mut names = Names { position, ..Names::default() };
+
+    for statement in statements {
+        names.visit(statement);
+    }
+
+    names
+}
+
+// The type the variable under the position has after its line ran
+fn hover(src: &str, position: (usize, usize)) -> Option<Json> {
+    let statements = statements(src)?;
+    let (name, span) = names(&statements, position).found?;
+
+    let ran: Vec<Node> = statements.into_iter().filter(|s| s.span.line <= span.line).collect();
+    let type_name = types::variable_types(&ran).get(&name).map_or("unknown", |t| t.name());
+
+    Some(Json::object(vec![
+        ("contents", Json::object(vec![
+            ("kind", "markdown".into()),
+            ("value", format!("`{}`: {}", name, type_name).as_str().into())
+        ])),
+        ("range", range(span))
+    ]))
+}
+
+// The first assignment of the variable under the position
+fn definition(src: &str, position: (usize, usize)) -> Option<Span> {
+    let statements = statements(src)?;
+    let names = names(&statements, position);
+    let (name, _) = names.found?;
+
+    names.definitions.get(&name).copied()
+}
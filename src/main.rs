@@ -1,41 +1,821 @@
 use std::fs;
 use std::env;
-use std::io::{Write, BufRead};
+use std::io::{IsTerminal, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
 
-mod interpreter;
-use interpreter::State;
+mod debugger;
+mod config;
+mod lsp;
+mod profile;
+mod coverage;
+use config::Config;
+use profile::{Profile, Profiler};
+use coverage::{Coverage, CoverageObserver};
+use term::color::{self, Color};
+use term::diagnostics::{Diagnostic, ErrorFormat};
+use term::json::Json;
+use term::plugin;
+use term::interpreter::{self, lint, types, CancelToken, Capabilities, Error, EvalMode, Hook, Node, OverflowPolicy, PassManager, RuntimeError, Snapshot, State, Timings, ValueKind, PRELUDE_NAMESPACE};
+use term::parser::{self, Partial};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+// Command line switches that can appear anywhere in the arguments
+struct Options {
+    trace: bool,
+    time: bool,
+    dump_ast: bool,
+    strict: bool,
+    // Runs scripts without any capabilities
+    sandbox: bool,
+    deny_warnings: bool,
+    color: bool,
+    overflow: OverflowPolicy,
+    // --eval=stack runs on the stack machine
+    eval_mode: EvalMode,
+    // Optimization level, -O0 or -O1
+    opt_level: u8,
+    error_format: ErrorFormat,
+    // Shared libraries whose builtins every state gets
+    plugins: Vec<String>,
+    // Where --profile collects its numbers
+    profile: Option<Arc<Mutex<Profile>>>,
+    // Which statements ran, with --coverage
+    coverage: Option<Arc<Mutex<Coverage>>>,
+    // Where --coverage=<path> writes an lcov tracefile
+    lcov: Option<String>,
+    // Whether states start with the standard library, off with --no-prelude
+    prelude: bool,
+    // How deeply expressions may nest, from --max-depth=N
+    max_depth: Option<usize>,
+    config: Config
+}
+
+impl Options {
+    // Removes the recognized switches from `args`
+    fn extract(args: &mut Vec<String>) -> Options {
+        let mut take = |flag: &str| match args.iter().position(|a| a == flag) {
+            Some(i) => {
+                args.remove(i);
+                true
+            },
+            None => false
+        };
+
+        let trace = take("--trace");
+        let time = take("--time");
+        let dump_ast = take("--dump-ast");
+        let strict = take("--strict");
+        let sandbox = take("--sandbox");
+        let deny_warnings = take("--deny-warnings");
+        let no_color = take("--no-color");
+        let profile = take("--profile").then(Arc::default);
+        let coverage = take("--coverage");
+        let no_prelude = take("--no-prelude");
+        let config = Config::load();
+
+        let opt_level = if take("-O0") { 0 } else { take("-O1"); 1 };
+        let overflow = take_value(args, "--overflow=");
+        let eval_mode = take_value(args, "--eval=");
+        let error_format = take_value(args, "--error-format=");
+        let plugins = take_all(args, "--plugin=");
+        let lcov = take_all(args, "--coverage=").pop();
+        let coverage = (coverage || lcov.is_some()).then(Arc::default);
+        let max_depth = take_all(args, "--max-depth=").pop().map(|depth| match depth.parse() {
+            Ok(levels) if levels > 0 => levels,
+            _ => {
+                eprintln!("Expected a positive number of levels for --max-depth, but got {:?}", depth);
+                std::process::exit(2)
+            }
+        });
+
+        Options {
+            trace,
+            time,
+            dump_ast,
+            strict,
+            sandbox: sandbox || config.sandbox,
+            deny_warnings,
+            color: !no_color && config.color && color::supported(),
+            overflow,
+            eval_mode,
+            opt_level,
+            error_format,
+            plugins,
+            profile,
+            coverage,
+            lcov,
+            prelude: !no_prelude,
+            max_depth,
+            config
+        }
+    }
+
+    // Interprets one line of `name`, reporting its timings if asked to and
+    // its error if there is one. A panic inside the interpreter is reported
+    // like an error, so a REPL session survives it
+    fn interpret(&self, name: &str, line_number: usize, line: &str, state: &mut State) -> Option<Timings> {
+        if let Some(profile) = &self.profile {
+            profile.lock().unwrap().first_line = line_number;
+        }
+
+        if let Some(coverage) = &self.coverage {
+            coverage.lock().unwrap().first_line = line_number;
+        }
+
+        match catch_panic(|| interpreter::interpret_timed(line, state), self.color)? {
+            Ok((_, timings)) => {
+                if self.time {
+                    print_timings(&format!("{}:{}", name, line_number), &timings);
+                }
+
+                Some(timings)
+            },
+            Err(e) => {
+                self.report(&e, name, line, line_number);
+                None
+            }
+        }
+    }
+
+    // Prints the error pointing into the source it happened in, where
+    // `first_line` is the line number `src` starts at
+    fn report(&self, e: &Error, name: &str, src: &str, first_line: usize) {
+        for diagnostic in Diagnostic::from_error(e) {
+            self.emit(&diagnostic, name, src, first_line);
+        }
+    }
+
+    fn emit(&self, diagnostic: &Diagnostic, name: &str, src: &str, first_line: usize) {
+        eprint!("{}", diagnostic.format(self.error_format, name, src, first_line, self.color));
+    }
+
+    // How deeply expressions may nest, for parsing without a state
+    fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(interpreter::DEFAULT_MAX_DEPTH)
+    }
+
+    fn new_state(&self) -> State {
+        let mut state = State::with_stack_size(self.config.stack_size);
+        // Before the hooks, so the prelude isn't traced or profiled
+        if self.prelude {
+            state.load_prelude();
+        }
+
+        state.overflow = self.overflow;
+        state.eval_mode = self.eval_mode;
+        state.passes = PassManager::for_level(self.opt_level);
+        state.set_strict(self.strict);
+
+        if let Some(depth) = self.max_depth {
+            state.set_max_call_depth(depth);
+        }
+
+        if self.sandbox {
+            state.capabilities = Capabilities::NONE;
+        }
+
+        if self.trace {
+            state.hook = Some(Box::new(Tracer { depth: 0 }));
+        }
+
+        if let Some(coverage) = &self.coverage {
+            state.add_observer(Box::new(CoverageObserver::new(coverage.clone())));
+        }
+
+        // There is only one hook, so profiling wins over tracing
+        if let Some(profile) = &self.profile {
+            state.hook = Some(Box::new(Profiler::new(profile.clone())));
+        }
+
+        for path in &self.plugins {
+            if let Err(e) = plugin::load(Path::new(path), &mut state) {
+                self.report(&e, path, "", 1);
+                std::process::exit(2);
+            }
+        }
+
+        state
+    }
+}
+
+// Runs `f`, turning a panic into None after printing it as an internal error
+// instead of the usual panic message
+fn catch_panic<T, F>(f: F, colored: bool) -> Option<T> where F: FnOnce() -> T {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        eprintln!("{} internal interpreter error: {}", color::paint("error:", Color::Red, colored), info);
+    }));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    panic::set_hook(previous);
+
+    result.ok()
+}
+
+// Every value of a switch that can be given several times
+fn take_all(args: &mut Vec<String>, prefix: &str) -> Vec<String> {
+    let mut values = Vec::new();
+
+    while let Some(i) = args.iter().position(|a| a.starts_with(prefix)) {
+        values.push(args.remove(i)[prefix.len()..].to_string());
+    }
+
+    values
+}
+
+// Switches with a value are written as --name=value. A missing switch gets
+// the default, and a bad value ends the program
+fn take_value<T>(args: &mut Vec<String>, prefix: &str) -> T where T: std::str::FromStr<Err = String> + Default {
+    match args.iter().position(|a| a.starts_with(prefix)) {
+        Some(i) => {
+            let value = args.remove(i);
+            value[prefix.len()..].parse().unwrap_or_else(|e: String| {
+                eprintln!("{}", e);
+                std::process::exit(2)
+            })
+        },
+        None => T::default()
+    }
+}
+
+// Prints every evaluated node when it is entered, and again along with its
+// result once its operands are done, indented by depth
+struct Tracer {
+    depth: usize
+}
+
+impl Hook for Tracer {
+    fn enter_node(&mut self, node: &Node, _state: &mut State) {
+        println!("{}{}", "  ".repeat(self.depth), node.entry);
+        self.depth += 1;
+    }
+
+    fn exit_node(&mut self, node: &Node, value: &ValueKind) {
+        self.depth -= 1;
+        println!("{}{} => {}", "  ".repeat(self.depth), node.entry, value);
+    }
+
+    fn fail_node(&mut self, node: &Node, _error: &RuntimeError) {
+        self.depth -= 1;
+        println!("{}{} failed", "  ".repeat(self.depth), node.entry);
+    }
+}
+
+fn print_variables(state: &State, options: &Options) {
+    for (name, value) in state.vars().filter(|(name, _)| !name.starts_with(PRELUDE_NAMESPACE)) {
+        let painted = color::paint(&value.to_string(), Color::of(value), options.color);
+        println!("Name: {}\nValue: {}\n\n", name, painted);
+    }
+}
+
+// What was waiting on the stack when the last input failed, top first and
+// numbered by depth. Between inputs the stack itself is always empty
+fn print_stack(state: &State, options: &Options) {
+    let stack = match state.stack_at_failure() {
+        None => return println!("The last input didn't fail, and the stack is empty between inputs"),
+        Some([]) => return println!("Nothing was waiting on the stack when the last input failed"),
+        Some(stack) => stack
+    };
+
+    println!("Waiting on the stack when the last input failed:");
+    for (i, value) in stack.iter().rev().enumerate() {
+        println!("{:>3}: {}", i, color::paint(&value.to_string(), Color::of(value), options.color));
+    }
+}
+
+fn print_timings(label: &str, timings: &Timings) {
+    println!(
+        "[time] {}: lex {:?}, parse {:?}, eval {:?}, total {:?}",
+        label, timings.lex, timings.parse, timings.eval, timings.total()
+    );
+}
+
+// Checks the whole source for syntax errors, then interprets it line by line,
+// stopping at the first runtime error. Warnings are only shown if `lint` is
+// set. Returns the lines that ran, so a REPL session can keep them in its
+// history, and whether everything succeeded
+fn run_source(name: &str, src: &str, state: &mut State, options: &Options, lint: bool) -> (Vec<String>, bool) {
+    let mut lines = Vec::new();
+    let mut total = Timings::default();
+
+    // The variables a file leaves behind are its output, so they only count
+    // as unused when warnings are denied
+    let warnings = match interpreter::check_limited(src, options.max_depth()) {
+        Ok(warnings) if lint => warnings.into_iter().filter(|w| options.deny_warnings || w.code != "unused-variable").collect(),
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            options.report(&e, name, src, 1);
+            return (lines, false);
+        }
+    };
+
+    for warning in &warnings {
+        options.emit(&Diagnostic::from_warning(warning), name, src, 1);
+    }
+
+    if options.deny_warnings && !warnings.is_empty() {
+        // JSON consumers only get records, the exit code tells them the rest
+        if options.error_format == ErrorFormat::Human {
+            eprintln!("{} not running {} because of warnings", color::paint("error:", Color::Red, options.color), name);
+        }
+        return (lines, false);
+    }
+
+    for (line_number, chunk) in statement_lines(src) {
+        match options.interpret(name, line_number, &chunk, state) {
+            Some(timings) => total += timings,
+            None => return (lines, false)
+        }
+        lines.push(chunk);
+    }
+
+    if options.time {
+        print_timings(name, &total);
+    }
+
+    (lines, true)
+}
+
+// Groups the lines of the source so a statement spanning several lines
+// stays together, along with the line number each group starts at
+fn statement_lines(src: &str) -> Vec<(usize, String)> {
+    let mut groups = Vec::new();
+    let mut pending = String::new();
+    let mut start = 0;
+
+    for (i, line) in src.lines().enumerate() {
+        if pending.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            start = i + 1;
+        } else {
+            pending.push('\n');
+        }
+
+        pending.push_str(line);
+
+        if !matches!(parser::parse_partial(&pending), Partial::Incomplete) {
+            groups.push((start, std::mem::take(&mut pending)));
+        }
+    }
+
+    if !pending.is_empty() {
+        groups.push((start, pending));
+    }
+
+    groups
+}
+
+fn run_file(path: &Path, state: &mut State, options: &Options, lint: bool) -> (Vec<String>, bool) {
+    let src = fs::read_to_string(path).expect("File not found!");
+
+    run_source(&path.display().to_string(), &src, state, options, lint)
+}
+
+// Prints the tree of every statement as a JSON array instead of running them
+fn dump_ast(path: &str, options: &Options) -> bool {
+    let src = fs::read_to_string(path).expect("File not found!");
+
+    match interpreter::parse_limited(&src, options.max_depth()) {
+        Ok(statements) => {
+            println!("{}", Json::Array(statements.iter().map(Node::to_json).collect()));
+            true
+        },
+        Err(e) => {
+            options.report(&e, path, &src, 1);
+            false
+        }
+    }
+}
+
+// Reports syntax errors and warnings without running the file, along with
+// operands of the wrong type if `types` is set. Returns whether there were
+// no errors
+fn check(path: &str, types: bool, options: &Options) -> bool {
+    let src = fs::read_to_string(path).expect("File not found!");
+
+    let warnings = match interpreter::check_limited(&src, options.max_depth()) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            options.report(&e, path, &src, 1);
+            return false;
+        }
+    };
+
+    for warning in &warnings {
+        options.emit(&Diagnostic::from_warning(warning), path, &src, 1);
+    }
+
+    let mut ok = !options.deny_warnings || warnings.is_empty();
+
+    if types {
+        let statements = interpreter::parse_limited(&src, options.max_depth()).unwrap_or_default();
+
+        for e in types::check_types(&statements) {
+            options.report(&Error::Runtime(e), path, &src, 1);
+            ok = false;
+        }
+    }
+
+    ok
+}
+
+// Reports every lint in the file except those allowed with --allow=, and
+// returns whether there were none
+fn lint(path: &str, args: &[String], state: &State, options: &Options) -> bool {
+    let allowed: Vec<&str> = args.iter()
+        .filter_map(|arg| arg.strip_prefix("--allow="))
+        .flat_map(|names| names.split(','))
+        .collect();
+
+    if let Some(unknown) = allowed.iter().find(|name| !lint::LINTS.contains(name)) {
+        eprintln!("Unknown lint '{}', expected one of {}", unknown, lint::LINTS.join(", "));
+        return false;
+    }
+
+    let src = fs::read_to_string(path).expect("File not found!");
+    let builtins: Vec<&str> = state.function_names().collect();
+
+    match lint::lint(&src, &builtins, &allowed) {
+        Ok(warnings) => {
+            for warning in &warnings {
+                options.emit(&Diagnostic::from_warning(warning), path, &src, 1);
+            }
+            warnings.is_empty()
+        },
+        Err(e) => {
+            options.report(&e, path, &src, 1);
+            false
+        }
+    }
+}
+
+// The coverage summary goes to stderr, and the lcov tracefile to the path
+// given with --coverage=
+fn write_coverage(path: &str, coverage: &Coverage, options: &Options) {
+    let src = fs::read_to_string(path).unwrap_or_default();
+    eprint!("{}", coverage.report(path, &src));
+
+    if let Some(lcov) = &options.lcov {
+        if let Err(e) = fs::write(lcov, coverage.lcov(path, &src)) {
+            eprintln!("Can't write {}: {}", lcov, e);
+        }
+    }
+}
+
+fn save_session(path: &str, history: &[String]) {
+    let mut contents = history.join("\n");
+    contents.push('\n');
+
+    match fs::write(path, contents) {
+        Ok(_) => println!("Session saved to {}", path),
+        Err(e) => println!("Can't save session to {}: {}", path, e)
+    }
+}
+
+// Prints the type of the expression's value. Expressions without effects
+// are evaluated, with no hook watching, so the type is the one the value
+// really has. Anything that assigns or calls only has its type inferred
+// from the types of the variables, so nothing it does happens
+fn print_type(expr: &str, state: &mut State, options: &Options) {
+    let error = |e: &dyn std::fmt::Display| eprintln!("{} {}", color::paint("error:", Color::Red, options.color), e);
+
+    let statements = match interpreter::parse_limited(expr, options.max_depth()) {
+        Ok(statements) => statements,
+        Err(e) => return error(&e)
+    };
+
+    if !statements.iter().any(interpreter::has_effect) {
+        let hook = state.hook.take();
+        let result = interpreter::interpret(expr, state);
+        state.hook = hook;
+
+        return match result {
+            Ok(value) => println!("{}", value.type_name()),
+            Err(e) => error(&e)
+        };
+    }
+
+    let variables = state.vars().map(|(name, value)| (name.to_string(), types::type_of(value))).collect();
+    match types::infer(&statements, variables) {
+        Ok(t) => println!("{}", t.name()),
+        Err(errors) => errors.iter().for_each(|e| error(e))
+    }
+}
+
+// A point :undo goes back to: the variables before an entry ran, and how
+// long the history was
+struct Checkpoint {
+    snapshot: Snapshot,
+    history_len: usize
+}
+
+// How many entries :undo can go back
+const MAX_UNDO: usize = 100;
+
+fn push_checkpoint(undo: &mut Vec<Checkpoint>, checkpoint: Checkpoint) {
+    if undo.len() == MAX_UNDO {
+        undo.remove(0);
+    }
+    undo.push(checkpoint);
+}
+
+// Prints the trees of the statements as JSON, like --dump-ast
+fn print_ast(src: &str, options: &Options) {
+    match interpreter::parse_limited(src, options.max_depth()) {
+        Ok(statements) => println!("{}", Json::Array(statements.iter().map(Node::to_json).collect())),
+        Err(e) => options.report(&e, "<repl>", src, 1)
+    }
+}
+
+// Prints every token with where it starts
+fn print_tokens(src: &str, options: &Options) {
+    match parser::lex(src) {
+        Ok(tokens) => {
+            for token in tokens {
+                println!("{:<6} {}", token.span.to_string(), token.kind);
+            }
+        },
+        Err(e) => options.report(&Error::from(e), "<repl>", src, 1)
+    }
+}
+
+// Returns true if the line was a REPL command rather than code
+fn run_command(line: &str, history: &mut Vec<String>, undo: &mut Vec<Checkpoint>, state: &mut State, options: &Options) -> bool {
+    let mut parts = line.splitn(2, ' ');
+
+    match parts.next() {
+        Some(":save") => {
+            match parts.next().map(str::trim) {
+                Some(path) if !path.is_empty() => save_session(path, history),
+                _ => println!("Usage: :save <file>")
+            }
+        },
+        Some(":reset") => {
+            state.reset();
+            if options.prelude {
+                state.load_prelude();
+            }
+            history.clear();
+            undo.clear();
+            println!("State cleared");
+        },
+        Some(":undo") => {
+            match undo.pop() {
+                Some(checkpoint) => {
+                    state.restore(checkpoint.snapshot);
+                    history.truncate(checkpoint.history_len);
+                    print_variables(state, options);
+                },
+                None => println!("Nothing to undo")
+            }
+        },
+        Some(":type") => {
+            match parts.next().map(str::trim) {
+                Some(expr) if !expr.is_empty() => print_type(expr, state, options),
+                _ => println!("Usage: :type <expr>")
+            }
+        },
+        Some(":ast") => {
+            match parts.next().map(str::trim) {
+                Some(src) if !src.is_empty() => print_ast(src, options),
+                _ => println!("Usage: :ast <code>")
+            }
+        },
+        Some(":tokens") => {
+            match parts.next().map(str::trim) {
+                Some(src) if !src.is_empty() => print_tokens(src, options),
+                _ => println!("Usage: :tokens <code>")
+            }
+        },
+        Some(":stack") => print_stack(state, options),
+        Some(cmd) if cmd.starts_with(':') => println!("Unknown command: {}", cmd),
+        _ => return false
+    }
+
+    true
+}
+
+// The token Ctrl-C cancels while the REPL is running
+static INTERRUPT: OnceLock<CancelToken> = OnceLock::new();
+
+#[cfg(unix)]
+fn catch_interrupts(token: CancelToken) {
+    const SIGINT: i32 = 2;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn on_interrupt(_: i32) {
+        if let Some(token) = INTERRUPT.get() {
+            token.cancel();
+        }
+    }
+
+    if INTERRUPT.set(token).is_ok() {
+        unsafe {
+            signal(SIGINT, on_interrupt);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn catch_interrupts(_token: CancelToken) {}
+
+// Terminals with bracketed paste mode enabled wrap pasted text in these
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+// Keeps reading lines until the end of the paste, so the whole snippet can
+// be evaluated as a single program
+fn read_paste(first: &str, stdin: &std::io::Stdin) -> String {
+    let mut paste = first.replacen(PASTE_START, "", 1);
+
+    while !paste.contains(PASTE_END) {
+        match stdin.read_line(&mut paste) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+
+    paste.replacen(PASTE_END, "", 1)
+}
+
+// Shown instead of the prompt while a statement goes on over several lines
+const CONTINUATION_PROMPT: &str = "... ";
+
+fn repl(state: &mut State, mut history: Vec<String>, options: &Options) {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
 
-    let mut main_state = State::new();
+    let bracketed_paste = stdout.is_terminal();
+    if bracketed_paste {
+        print!("\x1b[?2004h");
+    }
+
+    // Ctrl-C stops the line being evaluated instead of the whole session
+    catch_interrupts(state.cancel.clone());
 
-    if args.len() > 1 {
-        let input = fs::File::open(&args[1]).expect("File not found!");
-        let reader = std::io::BufReader::new(input);
+    let mut undo = Vec::new();
 
-        for line in reader.lines() {
-            interpreter::interpret(line.unwrap().as_str(), &mut main_state);
+    loop {
+        print!("{}", options.config.prompt);
+        stdout.flush().unwrap();
+        let mut input = String::new();
+        match stdin.read_line(&mut input) {
+            Ok(0) => break,
+            Ok(_) => {},
+            Err(text) => panic!("{}", text)
         }
 
-        for v in &main_state.variables {
-            println!("Name: {}\nValue: {:?}\n\n", v.0, v.1);
+        // An interrupt while waiting for input has nothing to stop
+        state.cancel.reset();
+
+        let checkpoint = Checkpoint { snapshot: state.snapshot(), history_len: history.len() };
+
+        if input.contains(PASTE_START) {
+            let paste = read_paste(&input, &stdin);
+            history.extend(run_source("<paste>", &paste, state, options, false).0);
+
+            if history.len() > checkpoint.history_len {
+                push_checkpoint(&mut undo, checkpoint);
+            }
+            print_variables(state, options);
+            continue;
         }
-    } else {
-        let stdin = std::io::stdin();
-        let mut stdout = std::io::stdout();
-        loop {
-            print!(">>> ");
+
+        let mut line = input.trim_end().to_string();
+
+        if line.trim().is_empty() || run_command(&line, &mut history, &mut undo, state, options) {
+            continue;
+        }
+
+        // An unclosed paren or string, or a trailing operator, asks for more
+        while let Partial::Incomplete = parser::parse_partial(&line) {
+            print!("{}", CONTINUATION_PROMPT);
             stdout.flush().unwrap();
-            let mut input = String::new();
-            interpreter::interpret(match stdin.read_line(&mut input) {
-                Ok(_) => input.as_str(),
+            input.clear();
+            match stdin.read_line(&mut input) {
+                Ok(0) => break,
+                Ok(_) => {},
                 Err(text) => panic!("{}", text)
-            }, &mut main_state);
+            }
+            line.push('\n');
+            line.push_str(input.trim_end());
+        }
 
-            for v in &main_state.variables {
-                println!("Name: {}\nValue: {:?}\n\n", v.0, v.1);
+        let line_number = history.iter().map(|entry| entry.lines().count()).sum::<usize>() + 1;
+
+        if options.interpret("<repl>", line_number, &line, state).is_some() {
+            history.push(line);
+            push_checkpoint(&mut undo, checkpoint);
+            print_variables(state, options);
+        }
+    }
+
+    if bracketed_paste {
+        print!("\x1b[?2004l");
+        stdout.flush().unwrap();
+    }
+}
+
+// Re-runs the script every time its modification time changes. The state is
+// rebuilt from scratch on each run unless `keep_state` is set
+fn watch(path: &str, keep_state: bool, options: &Options) {
+    let mut state = options.new_state();
+    let mut last_modified = None;
+
+    loop {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+
+            if !keep_state {
+                state = options.new_state();
             }
+
+            println!("--- Running {} ---", path);
+            run_file(Path::new(path), &mut state, options, true);
+            print_variables(&state, options);
+        }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let options = Options::extract(&mut args);
+
+    let mut main_state = options.new_state();
+
+    match args.get(1).map(String::as_str) {
+        Some("--resume") => {
+            let path = args.get(2).expect("Usage: term --resume <session file>");
+            let (history, _) = run_file(Path::new(path), &mut main_state, &options, false);
+            print_variables(&main_state, &options);
+            repl(&mut main_state, history, &options);
+        },
+        Some("watch") => {
+            let path = args.get(2).expect("Usage: term watch <file> [--keep-state]");
+            let keep_state = args.iter().skip(3).any(|a| a == "--keep-state");
+            watch(path, keep_state, &options);
+        },
+        Some("check") => {
+            let path = args.get(2).expect("Usage: term check <file> [--types]");
+            let types = args.iter().skip(3).any(|a| a == "--types");
+
+            if !check(path, types, &options) {
+                std::process::exit(1);
+            }
+        },
+        Some("lsp") => lsp::serve(),
+        Some("lint") => {
+            let path = args.get(2).expect("Usage: term lint <file> [--allow=<lint>,...]");
+
+            if !lint(path, &args[3..], &main_state, &options) {
+                std::process::exit(1);
+            }
+        },
+        Some("debug") => {
+            let path = args.get(2).expect("Usage: term debug <file>");
+            debugger::debug(path, &options);
+        },
+        Some(path) if options.dump_ast => {
+            if !dump_ast(path, &options) {
+                std::process::exit(1);
+            }
+        },
+        Some(path) => {
+            let (_, ok) = run_file(Path::new(path), &mut main_state, &options, true);
+            print_variables(&main_state, &options);
+
+            if let Some(profile) = &options.profile {
+                let src = fs::read_to_string(path).unwrap_or_default();
+                eprint!("{}", profile.lock().unwrap().report(&src));
+            }
+
+            if let Some(coverage) = &options.coverage {
+                write_coverage(path, &coverage.lock().unwrap(), &options);
+            }
+
+            if !ok {
+                std::process::exit(1);
+            }
+        },
+        None => {
+            // The init script only defines things, so it is kept out of the history
+            if let Some(init) = &options.config.init {
+                run_file(init, &mut main_state, &options, false);
+            }
+
+            repl(&mut main_state, Vec::new(), &options)
         }
     }
 }
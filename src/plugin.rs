@@ -0,0 +1,225 @@
+// Builtins from shared libraries loaded at runtime, e.g. with
+// `term --plugin=./libmath.so script.term`.
+//
+// A plugin exports a C function named `term_plugin_init`. It writes the
+// number of builtins to `count` and returns a pointer to that many
+// descriptors, which have to stay valid as long as the program runs, like
+// a static array. In C:
+//
+//     typedef struct {
+//         uint8_t kind;          /* one of the TERM_* kinds below */
+//         int64_t integer;
+//         double decimal;
+//         uint8_t boolean;
+//         const char *text;      /* NUL-terminated UTF-8 */
+//     } TermValue;
+//
+//     typedef struct {
+//         const char *name;
+//         uint8_t requires;      /* capability bits the builtin needs */
+//         int (*call)(const TermValue *args, size_t len, TermValue *out);
+//     } TermBuiltin;
+//
+//     const TermBuiltin *term_plugin_init(size_t *count);
+//
+// `call` returns 0 with the result in `out`, or anything else with an error
+// message in `out` as a string. Text in the arguments is only valid during
+// the call, and text in `out` is copied before the next call into the plugin
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::path::Path;
+use std::ptr;
+
+use crate::interpreter::{Capabilities, Error, State, ValueKind};
+
+pub const TERM_NONE: u8 = 0;
+pub const TERM_INTEGER: u8 = 1;
+pub const TERM_DECIMAL: u8 = 2;
+pub const TERM_BOOLEAN: u8 = 3;
+pub const TERM_STRING: u8 = 4;
+
+#[repr(C)]
+pub struct PluginValue {
+    pub kind: u8,
+    pub integer: i64,
+    pub decimal: f64,
+    pub boolean: u8,
+    pub text: *const c_char
+}
+
+#[repr(C)]
+pub struct PluginBuiltin {
+    pub name: *const c_char,
+    pub requires: u8,
+    // NULL in C, which is refused when loading
+    pub call: Option<CallFn>
+}
+
+type CallFn = extern "C" fn(args: *const PluginValue, len: usize, out: *mut PluginValue) -> c_int;
+
+type InitFn = unsafe extern "C" fn(count: *mut usize) -> *const PluginBuiltin;
+
+impl PluginValue {
+    fn none() -> PluginValue {
+        PluginValue { kind: TERM_NONE, integer: 0, decimal: 0.0, boolean: 0, text: ptr::null() }
+    }
+
+    // The caller keeps `text` alive for as long as the plugin may read it
+    fn from_value(value: &ValueKind, text: &mut Vec<CString>) -> PluginValue {
+        let mut plugin_value = PluginValue::none();
+
+        match value {
+            ValueKind::Integer(n) => {
+                plugin_value.kind = TERM_INTEGER;
+                plugin_value.integer = *n;
+            },
+            ValueKind::Decimal(n) => {
+                plugin_value.kind = TERM_DECIMAL;
+                plugin_value.decimal = *n;
+            },
+            // Plugins only know 64-bit integers, so larger ones are passed
+            // as the closest decimal
+            ValueKind::BigInt(n) => match n.to_i64() {
+                Some(n) => {
+                    plugin_value.kind = TERM_INTEGER;
+                    plugin_value.integer = n;
+                },
+                None => {
+                    plugin_value.kind = TERM_DECIMAL;
+                    plugin_value.decimal = n.to_f64();
+                }
+            },
+            ValueKind::Fixed(n) => {
+                plugin_value.kind = TERM_DECIMAL;
+                plugin_value.decimal = n.to_f64();
+            },
+            ValueKind::Rational(n, d) => {
+                plugin_value.kind = TERM_DECIMAL;
+                plugin_value.decimal = *n as f64 / *d as f64;
+            },
+            ValueKind::Boolean(b) => {
+                plugin_value.kind = TERM_BOOLEAN;
+                plugin_value.boolean = *b as u8;
+            },
+            ValueKind::Str(s) => {
+                let s = CString::new(s.replace('\0', "")).unwrap_or_default();
+                plugin_value.kind = TERM_STRING;
+                plugin_value.text = s.as_ptr();
+                text.push(s);
+            },
+            // Plugins only know C strings, which can't hold every byte
+            ValueKind::Bytes(_) | ValueKind::Identifier(_) | ValueKind::None => {}
+        }
+
+        plugin_value
+    }
+
+    // Text the plugin returned, copied out
+    unsafe fn text(&self) -> String {
+        if self.text.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(self.text).to_string_lossy().into_owned()
+        }
+    }
+
+    unsafe fn to_value(&self) -> Result<ValueKind, String> {
+        match self.kind {
+            TERM_NONE => Ok(ValueKind::None),
+            TERM_INTEGER => Ok(ValueKind::Integer(self.integer)),
+            TERM_DECIMAL => Ok(ValueKind::Decimal(self.decimal)),
+            TERM_BOOLEAN => Ok(ValueKind::Boolean(self.boolean != 0)),
+            TERM_STRING => Ok(ValueKind::from(self.text())),
+            other => Err(format!("Unknown value kind {} from a plugin", other))
+        }
+    }
+}
+
+fn call(f: CallFn, args: &[ValueKind]) -> Result<ValueKind, Error> {
+    let mut text = Vec::new();
+    let args: Vec<PluginValue> = args.iter().map(|arg| PluginValue::from_value(arg, &mut text)).collect();
+    let mut out = PluginValue::none();
+
+    let status = f(args.as_ptr(), args.len(), &mut out);
+
+    unsafe {
+        if status == 0 {
+            out.to_value().map_err(Error::Io)
+        } else {
+            Err(Error::Io(out.text()))
+        }
+    }
+}
+
+#[cfg(unix)]
+mod dl {
+    use std::ffi::{c_char, c_int, c_void, CStr};
+
+    pub const RTLD_NOW: c_int = 2;
+
+    #[link(name = "dl")]
+    extern "C" {
+        pub fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlerror() -> *const c_char;
+    }
+
+    pub fn error() -> String {
+        let message = unsafe { dlerror() };
+
+        if message.is_null() {
+            String::from("unknown error")
+        } else {
+            unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+        }
+    }
+}
+
+#[cfg(unix)]
+unsafe fn open(path: &Path) -> Result<InitFn, String> {
+    let filename = CString::new(path.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+
+    // The library is never closed, as its builtins stay registered
+    let handle: *mut c_void = dl::dlopen(filename.as_ptr(), dl::RTLD_NOW);
+    if handle.is_null() {
+        return Err(dl::error());
+    }
+
+    // A null symbol becomes None instead of a dangling function pointer
+    let init = dl::dlsym(handle, c"term_plugin_init".as_ptr());
+    std::mem::transmute::<*mut c_void, Option<InitFn>>(init).ok_or_else(|| String::from("it doesn't export term_plugin_init"))
+}
+
+#[cfg(not(unix))]
+unsafe fn open(_path: &Path) -> Result<InitFn, String> {
+    Err(String::from("plugins are only supported on Unix"))
+}
+
+// Loads the plugin and registers all of its builtins in the state, returning
+// how many there were
+pub fn load(path: &Path, state: &mut State) -> Result<usize, Error> {
+    let fail = |message: String| Error::Io(format!("Can't load plugin {}: {}", path.display(), message));
+
+    unsafe {
+        let init = open(path).map_err(fail)?;
+
+        let mut count = 0;
+        let builtins = init(&mut count);
+        if builtins.is_null() {
+            return Err(fail(String::from("term_plugin_init returned null")));
+        }
+
+        for builtin in std::slice::from_raw_parts(builtins, count) {
+            if builtin.name.is_null() {
+                return Err(fail(String::from("a builtin has no name")));
+            }
+
+            let name = CStr::from_ptr(builtin.name).to_string_lossy().into_owned();
+            let Some(f) = builtin.call else {
+                return Err(fail(format!("{} has no function to call", name)));
+            };
+            state.register_fn_requiring(&name, Capabilities::from_bits(builtin.requires), move |args| call(f, args));
+        }
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use term::interpreter::{Hook, Node, RuntimeError, State, ValueKind};
+
+// How often each expression ran and for how long, keyed by where it is in
+// the file: line, column and length
+#[derive(Default)]
+pub struct Profile {
+    // The line of the file the source being run starts at, as spans count
+    // from the start of that source
+    pub first_line: usize,
+    entries: HashMap<(usize, usize, usize), Entry>
+}
+
+#[derive(Default)]
+struct Entry {
+    count: u64,
+    // Time spent in the expression itself, without its operands
+    own: Duration,
+    total: Duration
+}
+
+// How many expressions the report shows
+const REPORT_SIZE: usize = 20;
+
+impl Profile {
+    // The lines that took the longest, then the expressions that took the
+    // longest themselves, leaving out their operands, with their source
+    // from `src`
+    pub fn report(&self, src: &str) -> String {
+        let source = |line: usize, col: usize, len: usize| -> String {
+            src.lines().nth(line - 1).unwrap_or("").chars().skip(col - 1).take(len.clamp(1, 40)).collect()
+        };
+
+        let mut lines: HashMap<usize, Duration> = HashMap::new();
+        for ((line, _, _), entry) in &self.entries {
+            *lines.entry(*line).or_default() += entry.own;
+        }
+
+        let mut lines: Vec<_> = lines.into_iter().collect();
+        lines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut out = format!("{:>12}  {:<10} source\n", "time", "line");
+
+        for (line, time) in lines.into_iter().take(REPORT_SIZE) {
+            out += &format!("{:>12.2?}  {:<10} {}\n", time, line, source(line, 1, usize::MAX));
+        }
+
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by(|a, b| b.1.own.cmp(&a.1.own).then(a.0.cmp(b.0)));
+
+        out += &format!("\n{:>12} {:>12} {:>10}  {:<10} source\n", "own", "total", "count", "location");
+
+        for ((line, col, len), entry) in entries.into_iter().take(REPORT_SIZE) {
+            out += &format!(
+                "{:>12.2?} {:>12.2?} {:>10}  {:<10} {}\n",
+                entry.own, entry.total, entry.count, format!("{}:{}", line, col), source(*line, *col, *len)
+            );
+        }
+
+        out
+    }
+}
+
+// Times every node as it is evaluated
+pub struct Profiler {
+    profile: Arc<Mutex<Profile>>,
+    // When each node being evaluated started and the time its operands took
+    // so far, innermost last
+    started: Vec<(Instant, Duration)>
+}
+
+impl Profiler {
+    pub fn new(profile: Arc<Mutex<Profile>>) -> Profiler {
+        Profiler { profile, started: Vec::new() }
+    }
+}
+
+impl Hook for Profiler {
+    fn enter_node(&mut self, _node: &Node, _state: &mut State) {
+        self.started.push((Instant::now(), Duration::ZERO));
+    }
+
+    fn exit_node(&mut self, node: &Node, _value: &ValueKind) {
+        let Some((started, operands)) = self.started.pop() else { return };
+        let elapsed = started.elapsed();
+
+        if let Some((_, parent_operands)) = self.started.last_mut() {
+            *parent_operands += elapsed;
+        }
+
+        let mut profile = self.profile.lock().unwrap();
+        let key = (profile.first_line + node.span.line - 1, node.span.col, node.span.len);
+        let entry = profile.entries.entry(key).or_default();
+        entry.count += 1;
+        entry.own += elapsed.saturating_sub(operands);
+        entry.total += elapsed;
+    }
+
+    // A node that failed isn't counted, as it never ran to the end
+    fn fail_node(&mut self, _node: &Node, _error: &RuntimeError) {
+        self.started.pop();
+    }
+}
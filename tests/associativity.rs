@@ -0,0 +1,68 @@
+use term::{Interpreter, Value};
+
+fn eval(src: &str) -> Value {
+    Interpreter::new().eval(src).unwrap()
+}
+
+fn assert_int(src: &str, expected: i64) {
+    match eval(src) {
+        Value::Integer(n) => assert_eq!(n, expected, "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+fn assert_rational(src: &str, expected: (i64, i64)) {
+    match eval(src) {
+        Value::Rational(n, d) => assert_eq!((n, d), expected, "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+#[test]
+fn subtraction_is_left_associative() {
+    assert_int("10 - 2 - 3", 5);
+    assert_int("1 - 1 - 1 - 1", -2);
+}
+
+#[test]
+fn division_is_left_associative() {
+    assert_int("8 / 4 / 2", 1);
+    assert_int("100 / 10 / 5 / 2", 1);
+    assert_rational("1 / 2 / 4", (1, 8));
+}
+
+#[test]
+fn mixed_chains_group_left() {
+    assert_int("10 - 2 + 3", 11);
+    assert_int("10 + 2 - 3", 9);
+    assert_int("12 / 3 * 2", 8);
+    assert_int("12 * 3 / 2", 18);
+}
+
+#[test]
+fn precedence_is_kept_within_chains() {
+    assert_int("10 - 2 * 3 - 1", 3);
+    assert_int("20 / 2 - 6 / 3", 8);
+    assert_int("-2 * 3 - 4", -10);
+}
+
+#[test]
+fn assignment_is_right_associative() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("a = b = 10 - 2 - 3").unwrap();
+
+    assert!(matches!(interpreter.get_var("a"), Some(Value::Integer(5))));
+    assert!(matches!(interpreter.get_var("b"), Some(Value::Integer(5))));
+}
+
+#[test]
+fn chains_are_folded_like_they_are_evaluated() {
+    let mut unoptimized = term::State::new();
+    unoptimized.passes = term::interpreter::PassManager::for_level(0);
+
+    let src = "100 - 20 - 5 / 5 / 1";
+    let folded = Interpreter::new().eval(src).unwrap();
+    let evaluated = Interpreter::with_state(unoptimized).eval(src).unwrap();
+
+    assert!(matches!((folded, evaluated), (Value::Integer(79), Value::Integer(79))));
+}
@@ -0,0 +1,37 @@
+use term::interpreter::OverflowPolicy;
+use term::{interpret, Error, State, ValueKind};
+
+fn eval(src: &str) -> Result<ValueKind, Error> {
+    let mut state = State::new();
+    state.overflow = OverflowPolicy::Promote;
+    interpret(src, &mut state)
+}
+
+#[test]
+fn even_division_stays_an_integer() {
+    match eval("x = 9223372036854775807 + 1\ny = x * 3 / 3") {
+        Ok(ValueKind::BigInt(n)) => assert_eq!(n.to_string(), "9223372036854775808"),
+        other => panic!("expected a big integer, got {:?}", other)
+    }
+}
+
+#[test]
+fn uneven_division_is_a_rational_when_it_fits() {
+    match eval("x = 9223372036854775807 + 1\ny = x / 6") {
+        Ok(ValueKind::Rational(n, d)) => assert_eq!((n, d), (1 << 62, 3)),
+        other => panic!("expected a rational, got {:?}", other)
+    }
+    // The fraction is reduced before it has to fit
+    match eval("x = 9223372036854775807 + 1\ny = x * 3 / (x * 2)") {
+        Ok(ValueKind::Rational(n, d)) => assert_eq!((n, d), (3, 2)),
+        other => panic!("expected a rational, got {:?}", other)
+    }
+}
+
+#[test]
+fn uneven_division_that_doesnt_fit_overflows() {
+    match eval("x = 9223372036854775807 + 1\ny = x / 3") {
+        Err(Error::Runtime(e)) => assert_eq!(e.kind.code(), "overflow"),
+        other => panic!("expected an overflow, got {:?}", other)
+    }
+}
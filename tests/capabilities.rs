@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use term::interpreter::Capabilities;
+use term::{Error, Interpreter, Value};
+
+fn assert_denied(result: Result<Value, Error>, message: &str) {
+    match result {
+        Err(Error::Runtime(e)) => {
+            assert_eq!(e.kind.code(), "capability-denied");
+            assert_eq!(e.kind.to_string(), message);
+        },
+        other => panic!("expected a denied capability, got {:?}", other)
+    }
+}
+
+// A builtin needing `requires`, counting how often it actually ran
+fn counted(interpreter: &mut Interpreter, name: &str, requires: Capabilities) -> Arc<AtomicUsize> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    interpreter.register_fn_requiring(name, requires, move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(Value::Integer(1))
+    });
+    calls
+}
+
+#[test]
+fn everything_is_granted_by_default() {
+    let mut interpreter = Interpreter::new();
+    let calls = counted(&mut interpreter, "fetch", Capabilities::NETWORK | Capabilities::FILESYSTEM);
+
+    assert!(matches!(interpreter.eval("x = fetch()"), Ok(Value::Integer(1))));
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn the_sandbox_refuses_builtins_with_side_effects() {
+    let mut interpreter = Interpreter::new();
+    let calls = counted(&mut interpreter, "fetch", Capabilities::NETWORK);
+    interpreter.set_capabilities(Capabilities::NONE);
+
+    assert_denied(interpreter.eval("x = fetch()"), "fetch is not allowed here, it needs: network");
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+    assert!(interpreter.get_var("x").is_none());
+
+    // Builtins without side effects still run
+    assert!(matches!(interpreter.eval("x = len(\"abc\")"), Ok(Value::Integer(3))));
+}
+
+#[test]
+fn only_the_missing_capabilities_are_reported() {
+    let mut interpreter = Interpreter::new();
+    counted(&mut interpreter, "sync", Capabilities::FILESYSTEM | Capabilities::NETWORK | Capabilities::ENV);
+    interpreter.set_capabilities(Capabilities::NETWORK);
+
+    assert_denied(interpreter.eval("x = sync()"), "sync is not allowed here, it needs: filesystem, env");
+}
+
+#[test]
+fn file_builtins_need_the_filesystem() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_capabilities(Capabilities::ALL.missing_from(Capabilities::FILESYSTEM));
+
+    assert_denied(interpreter.eval("x = read_bytes(\"/nonexistent\")"), "read_bytes is not allowed here, it needs: filesystem");
+}
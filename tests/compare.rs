@@ -0,0 +1,83 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use term::{interpret, Error, State, ValueKind};
+
+fn eval(src: &str) -> ValueKind {
+    interpret(&format!("x = {}", src), &mut State::new()).unwrap_or_else(|e| panic!("{} failed: {}", src, e))
+}
+
+fn hash(value: &ValueKind) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The same number written as every kind of number there is
+const ONES: &[&str] = &["1", "1.0", "1n", "1.0d", "1.00d", "2 / 2", "3 / 3 * 1"];
+
+#[test]
+fn every_kind_of_number_equals_the_same_value() {
+    let ones: Vec<ValueKind> = ONES.iter().map(|src| eval(src)).collect();
+
+    for (a, a_src) in ones.iter().zip(ONES) {
+        for (b, b_src) in ones.iter().zip(ONES) {
+            assert_eq!(a, b, "{} and {}", a_src, b_src);
+            assert_eq!(hash(a), hash(b), "{} and {}", a_src, b_src);
+        }
+    }
+}
+
+#[test]
+fn fractions_equal_and_hash_like_their_decimals() {
+    for (a, b) in [("1 / 2", "0.5"), ("1 / 2", "0.5d"), ("1 / 4", "0.25d"), ("-3 / 2", "-1.5")] {
+        assert_eq!(eval(a), eval(b), "{} and {}", a, b);
+        assert_eq!(hash(&eval(a)), hash(&eval(b)), "{} and {}", a, b);
+    }
+}
+
+#[test]
+fn numbers_are_compared_exactly() {
+    assert_ne!(eval("0.1 + 0.2"), eval("0.3d"));
+    assert_ne!(eval("1 / 3"), eval("0.3333333333333333"));
+    // 2^53 + 1 has no exact decimal
+    assert!(eval("9007199254740993") > eval("9007199254740992.0"));
+    assert!(eval("9223372036854775807n * 2") > eval("9223372036854775807"));
+    assert!(eval("1 / 3") < eval("0.34d"));
+    assert!(eval("-1 / 3") > eval("-0.34"));
+}
+
+#[test]
+fn nan_equals_nothing_and_infinity_is_beyond_everything() {
+    let nan = ValueKind::Decimal(f64::NAN);
+    let inf = ValueKind::Decimal(f64::INFINITY);
+
+    assert_ne!(nan, nan);
+    assert_eq!(nan.partial_cmp(&eval("1")), None);
+    assert!(inf > eval("9223372036854775807n * 9223372036854775807n"));
+    assert!(ValueKind::Decimal(f64::NEG_INFINITY) < eval("-1 / 3"));
+}
+
+#[test]
+fn other_kinds_only_equal_themselves() {
+    assert_eq!(eval("\"a\""), eval("\"a\""));
+    assert!(eval("\"a\"") < eval("\"b\""));
+    assert!(eval("false") < eval("true"));
+    assert_eq!(eval("none"), eval("none"));
+
+    assert_ne!(eval("\"1\""), eval("1"));
+    assert_ne!(eval("true"), eval("1"));
+    assert_ne!(eval("none"), eval("0"));
+    assert_eq!(eval("\"1\"").partial_cmp(&eval("1")), None);
+}
+
+#[test]
+fn ordering_different_kinds_is_an_error() {
+    assert!(matches!(eval("1 == 1.0d"), ValueKind::Boolean(true)));
+    assert!(matches!(eval("\"1\" == 1"), ValueKind::Boolean(false)));
+
+    match interpret("x = \"a\" < 1", &mut State::new()) {
+        Err(Error::Runtime(e)) => assert_eq!(e.kind.code(), "type-error"),
+        other => panic!("expected a type error, got {:?}", other)
+    }
+}
@@ -0,0 +1,130 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use term::interpreter::optimize::AstPass;
+use term::interpreter::{check_limited, EvalMode, RuntimeErrorKind, DEFAULT_MAX_DEPTH};
+use term::parser::{Node, TokenKind};
+use term::{check, interpret, Error, State, ValueKind};
+
+// `x = --...-y`, which constant folding can't flatten
+fn nested(levels: usize) -> String {
+    format!("y = 1\nx = {}y", "-".repeat(levels))
+}
+
+fn assert_syntax_error(result: Result<ValueKind, Error>, src: &str) {
+    match result {
+        Err(Error::Syntax(errors)) => assert_eq!(errors[0].message, "Expressions nest too deeply"),
+        other => panic!("{:.40}... gave {:?}", src, other.map(|_| ()))
+    }
+}
+
+// Nesting the parser can't handle even on the small stacks of test threads,
+// in every shape it comes in
+fn deep_sources() -> Vec<String> {
+    vec![
+        format!("x = {}1{}", "(".repeat(5000), ")".repeat(5000)),
+        format!("x = {}", vec!["1"; 5000].join(" + ")),
+        format!("x = {}1", "-".repeat(5000)),
+        format!("x = {}1", "a = ".repeat(5000)),
+        format!("x = {}1{}", "len(".repeat(5000), ")".repeat(5000))
+    ]
+}
+
+#[test]
+fn deep_source_is_a_syntax_error_in_every_mode() {
+    for mode in [EvalMode::Tree, EvalMode::Stack] {
+        for src in deep_sources() {
+            let mut state = State::new();
+            state.eval_mode = mode;
+            assert_syntax_error(interpret(&src, &mut state), &src);
+        }
+    }
+}
+
+#[test]
+fn deep_source_is_a_syntax_error_when_checked() {
+    for src in deep_sources() {
+        assert!(matches!(check(&src), Err(Error::Syntax(_))), "{:.40}...", src);
+    }
+}
+
+#[test]
+fn nesting_up_to_the_limit_runs() {
+    let src = format!("x = {}", vec!["1"; DEFAULT_MAX_DEPTH - 1].join(" + "));
+
+    for mode in [EvalMode::Tree, EvalMode::Stack] {
+        let mut state = State::with_stack_size(DEFAULT_MAX_DEPTH);
+        state.eval_mode = mode;
+        assert!(matches!(interpret(&src, &mut state), Ok(ValueKind::Integer(n)) if n == DEFAULT_MAX_DEPTH as i64 - 1));
+    }
+}
+
+#[test]
+fn the_limit_is_configurable() {
+    let mut state = State::new();
+    state.set_max_call_depth(20);
+
+    assert!(interpret(&nested(5), &mut state).is_ok());
+    assert_syntax_error(interpret(&nested(40), &mut state), "nested(40)");
+}
+
+// Wraps the value of every assignment in `0 + (...)` a number of times,
+// making trees deeper than what was parsed
+struct Deepen(usize);
+
+impl AstPass for Deepen {
+    fn name(&self) -> &'static str {
+        "deepen"
+    }
+
+    fn run(&mut self, statements: &mut [Node], _state: &State) {
+        for statement in statements.iter_mut().filter(|s| matches!(s.entry, TokenKind::Assign)) {
+            for _ in 0..self.0 {
+                let mut zero = Node::new();
+                zero.entry = TokenKind::Integer(0);
+
+                let mut plus = Node::new();
+                plus.entry = TokenKind::Plus;
+                plus.children = vec![zero, std::mem::take(&mut statement.children[1])];
+                statement.children[1] = plus;
+            }
+        }
+    }
+}
+
+#[test]
+fn checking_uses_the_given_limit() {
+    let src = format!("x = {}1{}", "(".repeat(300), ")".repeat(300));
+
+    assert!(matches!(check(&src), Err(Error::Syntax(_))));
+    assert!(check_limited(&src, 1000).is_ok());
+}
+
+#[test]
+fn trees_deepened_after_parsing_are_stopped_while_evaluating() {
+    let mut state = State::new();
+    state.set_max_call_depth(10);
+    state.passes.register(Box::new(Deepen(40)));
+
+    match interpret("x = 1", &mut state) {
+        Err(Error::Runtime(e)) => match e.kind {
+            RuntimeErrorKind::TooDeep(10, frames) => assert_eq!(frames.len(), 4),
+            other => panic!("expected a depth error, got {:?}", other)
+        },
+        other => panic!("expected a runtime error, got {:?}", other)
+    }
+}
+
+#[test]
+fn a_panicking_builtin_leaves_no_frames_behind() {
+    let mut state = State::new();
+    state.set_max_call_depth(30);
+    state.register_fn("boom", |_| panic!("boom"));
+
+    for _ in 0..50 {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| interpret("x = 1 + -(2 + -boom())", &mut state)));
+        assert!(result.is_err());
+    }
+
+    assert!(state.stack_contents().is_empty());
+    assert!(interpret(&nested(20), &mut state).is_ok());
+}
@@ -0,0 +1,69 @@
+use term::{Error, Interpreter, Value};
+
+fn format(src: &str) -> Result<String, Error> {
+    match Interpreter::new().eval(src)? {
+        Value::Str(s) => Ok(s.to_string()),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+fn assert_formats(src: &str, expected: &str) {
+    assert_eq!(format(src).unwrap(), expected, "{}", src);
+}
+
+fn assert_format_error(src: &str, message: &str) {
+    match format(src) {
+        Err(Error::Runtime(e)) => {
+            assert_eq!(e.kind.code(), "format-error", "{}", src);
+            assert!(e.kind.to_string().contains(message), "{} gave {}", src, e.kind);
+        },
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+#[test]
+fn directives_format_their_value() {
+    assert_formats(r#"x = "n=%d" % 42"#, "n=42");
+    assert_formats(r#"x = "%x" % 255"#, "ff");
+    assert_formats(r#"x = "%f" % 1.5"#, "1.500000");
+    assert_formats(r#"x = "%.2f" % 2.675d"#, "2.68");
+    assert_formats(r#"x = "%s!" % "hi""#, "hi!");
+    assert_formats(r#"x = "%.3s" % "abcdef""#, "abc");
+}
+
+#[test]
+fn double_percent_is_a_percent_sign() {
+    assert_formats(r#"x = "%d%%" % 50"#, "50%");
+    assert_formats(r#"x = "%%%s%%" % "a""#, "%a%");
+}
+
+#[test]
+fn width_pads_on_either_side() {
+    assert_formats(r#"x = "[%5d]" % 42"#, "[   42]");
+    assert_formats(r#"x = "[%-5d]" % 42"#, "[42   ]");
+    assert_formats(r#"x = "[%8.3f]" % 3.14159"#, "[   3.142]");
+    assert_formats(r#"x = "[%2s]" % "long""#, "[long]");
+}
+
+#[test]
+fn the_number_of_directives_has_to_match() {
+    assert_format_error(r#"x = "%d and %d" % 1"#, "more directives");
+    assert_format_error(r#"x = "no directive" % 1"#, "no directive");
+    assert_format_error(r#"x = "100%%" % 1"#, "no directive");
+}
+
+#[test]
+fn sizes_are_checked() {
+    assert_formats(r#"x = "%1000d" % 1"#, &format!("{:>1000}", 1));
+    assert_format_error(r#"x = "%1001d" % 1"#, "width");
+    assert_format_error(r#"x = "%99999999999999999999999d" % 1"#, "width");
+    assert_format_error(r#"x = "%.1001f" % 1"#, "precision");
+    assert_format_error(r#"x = "%.f" % 1"#, "precision");
+}
+
+#[test]
+fn directives_check_their_value() {
+    assert_format_error(r#"x = "%d" % "a""#, "needs an integer");
+    assert_format_error(r#"x = "%q" % 1"#, "unknown directive");
+    assert_format_error(r#"x = "%5" % 1"#, "ends in the middle");
+}
@@ -0,0 +1,83 @@
+use term::parser::{lex, LexErrorKind};
+
+// Every operator and punctuation token, as written in source
+const OPERATORS: &[&str] = &["==", "!=", ".", "=", "!", "<", ">", "+", "-", "*", "/", "%", "(", ")", "\n", ";", ","];
+
+// The tokens of `src` the way they print, with newlines as \n again
+fn tokens(src: &str) -> Vec<String> {
+    lex(src).unwrap_or_else(|e| panic!("{:?} failed to lex: {}", src, e))
+        .iter()
+        .map(|t| t.kind.to_string().replace("newline", "\n"))
+        .collect()
+}
+
+#[test]
+fn every_operator_lexes_alone() {
+    for op in OPERATORS {
+        assert_eq!(tokens(op), [*op], "{:?}", op);
+    }
+}
+
+#[test]
+fn adjacent_operators_split_at_the_longest_match() {
+    // Pairs that read as something else: a longer operator, or a comment
+    let merged: &[(&str, &str, &[&str])] = &[
+        ("=", "=", &["=="]),
+        ("!", "=", &["!="]),
+        ("=", "==", &["==", "="]),
+        ("!", "==", &["!=", "="]),
+        ("/", "/", &[]),
+        ("/", "*", &[])
+    ];
+
+    for a in OPERATORS {
+        for b in OPERATORS {
+            let src = format!("{}{}", a, b);
+            let expected: Vec<&str> = match merged.iter().find(|(x, y, _)| x == a && y == b) {
+                Some((_, _, expected)) => expected.to_vec(),
+                None => vec![*a, *b]
+            };
+
+            assert_eq!(tokens(&src), expected, "{:?}", src);
+        }
+    }
+}
+
+#[test]
+fn spaces_keep_operators_apart() {
+    for a in OPERATORS {
+        for b in OPERATORS {
+            let src = format!("{} {}", a, b);
+            assert_eq!(tokens(&src), [*a, *b], "{:?}", src);
+        }
+    }
+}
+
+#[test]
+fn operators_get_their_own_length() {
+    let tokens = lex("a==b != !c").unwrap();
+    let lengths: Vec<usize> = tokens.iter().map(|t| t.span.len).collect();
+
+    assert_eq!(lengths, [1, 2, 1, 2, 1, 1]);
+}
+
+#[test]
+fn operators_mix_with_other_tokens() {
+    assert_eq!(tokens("x=-1"), ["x", "=", "-", "1"]);
+    assert_eq!(tokens("f(a,b);"), ["f", "(", "a", ",", "b", ")", ";"]);
+    assert_eq!(tokens("\"%d\"%5"), ["\"%d\"", "%", "5"]);
+    assert_eq!(tokens("a!==b"), ["a", "!=", "=", "b"]);
+    assert_eq!(tokens("1.5.x"), ["1.5", ".", "x"]);
+}
+
+#[test]
+fn keywords_only_match_whole_words() {
+    assert_eq!(tokens("if iffy while_ truest false"), ["if", "iffy", "while_", "truest", "false"]);
+}
+
+#[test]
+fn unknown_characters_are_reported() {
+    let e = lex("a & b").unwrap_err();
+    assert_eq!(e.kind, LexErrorKind::UnknownCharacter('&'));
+    assert_eq!(e.span.col, 3);
+}
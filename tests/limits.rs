@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use term::interpreter::Capabilities;
+use term::{Error, Interpreter, Value};
+
+// Evaluating `x = a + a + ... + a` takes two operations per `+ a`
+fn sum(terms: usize) -> String {
+    format!("x = {}", vec!["a"; terms].join(" + "))
+}
+
+fn assert_stopped(result: Result<Value, Error>, code: &str) {
+    match result {
+        Err(Error::Runtime(e)) => assert_eq!(e.kind.code(), code),
+        other => panic!("expected {}, got {:?}", code, other)
+    }
+}
+
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_var("a", 1i64);
+    interpreter
+}
+
+#[test]
+fn the_operation_limit_applies_to_each_call() {
+    let mut interpreter = interpreter();
+    interpreter.set_limits(100, Duration::from_secs(60));
+
+    assert!(matches!(interpreter.eval(&sum(10)), Ok(Value::Integer(10))));
+    assert!(matches!(interpreter.eval(&sum(10)), Ok(Value::Integer(10))));
+    assert_stopped(interpreter.eval(&sum(100)), "limit-exceeded");
+    // The failed call left nothing behind
+    assert!(matches!(interpreter.get_var("x"), Some(Value::Integer(10))));
+    assert!(matches!(interpreter.eval(&sum(10)), Ok(Value::Integer(10))));
+}
+
+#[test]
+fn the_time_limit_stops_long_evaluations() {
+    let mut interpreter = interpreter();
+    interpreter.set_limits(u64::MAX, Duration::ZERO);
+
+    // Checked every few hundred operations, across statements
+    assert_stopped(interpreter.eval(&vec![sum(100); 10].join("\n")), "limit-exceeded");
+}
+
+#[test]
+fn a_cancelled_token_stops_evaluation_until_reset() {
+    let mut interpreter = interpreter();
+    let token = interpreter.cancel_token();
+
+    token.cancel();
+    assert_stopped(interpreter.eval("x = 1"), "interrupted");
+    assert_stopped(interpreter.eval("x = 1"), "interrupted");
+
+    token.reset();
+    assert!(matches!(interpreter.eval("x = 1"), Ok(Value::Integer(1))));
+}
+
+#[test]
+fn cancelling_mid_evaluation_stops_at_the_next_operation() {
+    let mut interpreter = interpreter();
+    let token = interpreter.cancel_token();
+    interpreter.register_fn("stop", move |_| {
+        token.cancel();
+        Ok(Value::Integer(0))
+    });
+
+    assert_stopped(interpreter.eval("x = stop() + a"), "interrupted");
+    assert!(interpreter.get_var("x").is_none());
+}
+
+#[test]
+fn cancelling_interrupts_a_waiting_receive() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let mut interpreter = interpreter();
+    interpreter.set_capabilities(Capabilities::ALL);
+    let token = interpreter.cancel_token();
+
+    // The peer accepts but never sends anything
+    let peer = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        token.cancel();
+        stream
+    });
+
+    assert_stopped(interpreter.eval(&format!("c = tcp_connect(\"127.0.0.1\", {})\ntcp_recv(c)", port)), "interrupted");
+    drop(peer.join().unwrap());
+}
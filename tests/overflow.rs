@@ -0,0 +1,79 @@
+use term::interpreter::OverflowPolicy;
+use term::{interpret, Error, State, ValueKind};
+
+// i64::MIN, which has no literal of its own
+const MIN: &str = "m = 0 - 9223372036854775807 - 1\n";
+
+fn eval(src: &str, overflow: OverflowPolicy) -> Result<ValueKind, Error> {
+    let mut state = State::new();
+    state.overflow = overflow;
+    interpret(src, &mut state)
+}
+
+fn assert_int(src: &str, overflow: OverflowPolicy, expected: i64) {
+    match eval(src, overflow) {
+        Ok(ValueKind::Integer(n)) => assert_eq!(n, expected, "{} under {:?}", src, overflow),
+        other => panic!("{} evaluated to {:?} under {:?}", src, other, overflow)
+    }
+}
+
+fn assert_overflow(src: &str) {
+    match eval(src, OverflowPolicy::Error) {
+        Err(Error::Runtime(e)) => assert_eq!(e.kind.code(), "overflow", "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+#[test]
+fn errors_by_default() {
+    assert_overflow("x = 9223372036854775807 + 1");
+    assert_overflow(&format!("{}x = m - 1", MIN));
+    assert_overflow("x = 9223372036854775807 * 2");
+    assert_overflow(&format!("{}x = m / -1", MIN));
+}
+
+#[test]
+fn wraps_around() {
+    assert_int("x = 9223372036854775807 + 1", OverflowPolicy::Wrap, i64::MIN);
+    assert_int(&format!("{}x = m - 1", MIN), OverflowPolicy::Wrap, i64::MAX);
+    assert_int("x = 9223372036854775807 * 2", OverflowPolicy::Wrap, -2);
+    assert_int(&format!("{}x = m / -1", MIN), OverflowPolicy::Wrap, i64::MIN);
+}
+
+#[test]
+fn saturates_at_the_bounds() {
+    assert_int("x = 9223372036854775807 + 1", OverflowPolicy::Saturate, i64::MAX);
+    assert_int(&format!("{}x = m - 1", MIN), OverflowPolicy::Saturate, i64::MIN);
+    assert_int(&format!("{}x = m * 2", MIN), OverflowPolicy::Saturate, i64::MIN);
+    assert_int(&format!("{}x = m / -1", MIN), OverflowPolicy::Saturate, i64::MAX);
+}
+
+#[test]
+fn results_that_fit_are_unaffected() {
+    for policy in [OverflowPolicy::Error, OverflowPolicy::Wrap, OverflowPolicy::Saturate, OverflowPolicy::Promote] {
+        assert_int("x = 9223372036854775806 + 1", policy, i64::MAX);
+        assert_int(&format!("{}x = m / 1", MIN), policy, i64::MIN);
+    }
+}
+
+fn assert_big(src: &str, expected: &str) {
+    match eval(src, OverflowPolicy::Promote) {
+        Ok(ValueKind::BigInt(n)) => assert_eq!(n.to_string(), expected, "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+#[test]
+fn promotes_to_big_integers() {
+    assert_big("x = 9223372036854775807 + 1", "9223372036854775808");
+    assert_big(&format!("{}x = m - 1", MIN), "-9223372036854775809");
+    assert_big("x = 9223372036854775807 * 9223372036854775807", "85070591730234615847396907784232501249");
+    assert_big(&format!("{}x = m / -1", MIN), "9223372036854775808");
+}
+
+#[test]
+fn promoted_results_that_fit_are_integers_again() {
+    assert_int("x = 9223372036854775807 + 1\ny = x - 1", OverflowPolicy::Promote, i64::MAX);
+    assert_int("x = 9223372036854775807 * 4\ny = x / 2 - 9223372036854775807", OverflowPolicy::Promote, i64::MAX);
+    assert_int(&format!("{}x = m - 1\ny = x + 1", MIN), OverflowPolicy::Promote, i64::MIN);
+}
@@ -0,0 +1,58 @@
+use term::interpreter::parse;
+use term::parser::Node;
+use term::Error;
+
+// The tree as nested prefix expressions, like (+ 1 (* 2 3))
+fn shape(node: &Node) -> String {
+    if node.children.is_empty() {
+        return node.entry.to_string();
+    }
+
+    let children: Vec<String> = node.children.iter().map(shape).collect();
+    format!("({} {})", node.entry, children.join(" "))
+}
+
+fn assert_parses(src: &str, expected: &str) {
+    let statements = parse(src).unwrap_or_else(|e| panic!("{} failed to parse: {:?}", src, e));
+    assert_eq!(shape(&statements[0]), expected, "{}", src);
+}
+
+#[test]
+fn tighter_operators_bind_first() {
+    assert_parses("x = 1 + 2 * 3", "(= x (+ 1 (* 2 3)))");
+    assert_parses("x = 1 * 2 + 3", "(= x (+ (* 1 2) 3))");
+    assert_parses("x = 1 + 2 % 3 / 4", "(= x (+ 1 (/ (% 2 3) 4)))");
+    assert_parses("x = 1 + 2 == 3 * 1", "(= x (== (+ 1 2) (* 3 1)))");
+    assert_parses("x = 1 < 2 == true", "(= x (== (< 1 2) true))");
+}
+
+#[test]
+fn parentheses_override_precedence() {
+    assert_parses("x = (1 + 2) * 3", "(= x (* (+ 1 2) 3))");
+    assert_parses("x = 2 * (3 - (4 - 5))", "(= x (* 2 (- 3 (- 4 5))))");
+}
+
+#[test]
+fn prefix_operators_bind_tighter_than_any_binary_one() {
+    assert_parses("x = -2 * 3", "(= x (* (- 0 2) 3))");
+    assert_parses("x = 2 * -3", "(= x (* 2 (- 0 3)))");
+    assert_parses("x = - -2", "(= x (- 0 (- 0 2)))");
+    assert_parses("x = +2 + 3", "(= x (+ (+ 0 2) 3))");
+}
+
+#[test]
+fn assignment_groups_right() {
+    assert_parses("a = b = 1 + 2", "(= a (= b (+ 1 2)))");
+}
+
+#[test]
+fn calls_are_terms() {
+    assert_parses("x = 2 * len(\"ab\") + 1", "(= x (+ (* 2 (( len \"ab\")) 1))");
+}
+
+#[test]
+fn a_missing_operand_is_a_syntax_error() {
+    for src in ["x = 1 +", "x = * 2", "x = (1 + 2", "x = 1 + 2)"] {
+        assert!(matches!(parse(src), Err(Error::Syntax(_))), "{}", src);
+    }
+}
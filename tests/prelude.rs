@@ -0,0 +1,40 @@
+use term::{Error, Interpreter, Value};
+
+fn assert_read_only(result: Result<Value, Error>) {
+    match result {
+        Err(Error::Runtime(e)) => assert_eq!(e.kind.code(), "read-only"),
+        other => panic!("expected a read-only error, got {:?}", other)
+    }
+}
+
+#[test]
+fn prelude_names_are_read_only() {
+    let mut interpreter = Interpreter::new();
+
+    assert_read_only(interpreter.eval("std.pi = 3"));
+    assert_read_only(interpreter.eval("std.mine = 1"));
+    assert!(matches!(interpreter.get_var("std.pi"), Some(Value::Decimal(pi)) if *pi == std::f64::consts::PI));
+    assert!(interpreter.get_var("std.mine").is_none());
+}
+
+#[test]
+fn a_prelude_can_define_names_under_std() {
+    let mut interpreter = Interpreter::builder().extend_prelude("std.answer = 42").build().unwrap();
+
+    assert!(matches!(interpreter.eval("std.answer"), Ok(Value::Integer(42))));
+    assert_read_only(interpreter.eval("std.answer = 0"));
+}
+
+#[test]
+fn a_built_interpreter_has_the_constants_of_a_new_one() {
+    for prelude in [None, Some("")] {
+        let builder = Interpreter::builder();
+        let mut interpreter = match prelude {
+            Some(source) => builder.with_prelude(source),
+            None => builder
+        }.build().unwrap();
+
+        assert!(matches!(interpreter.eval("x = std.inf > 1"), Ok(Value::Boolean(true))));
+        assert!(matches!(interpreter.get_var("std.nan"), Some(Value::Decimal(n)) if n.is_nan()));
+    }
+}
@@ -0,0 +1,67 @@
+use term::interpreter::OverflowPolicy;
+use term::{interpret, Error, State, ValueKind};
+
+// i64::MIN, which has no literal of its own
+const MIN: &str = "m = 0 - 9223372036854775807 - 1\n";
+
+fn eval_with(src: &str, overflow: OverflowPolicy) -> Result<ValueKind, Error> {
+    let mut state = State::new();
+    state.overflow = overflow;
+    interpret(src, &mut state)
+}
+
+fn eval(src: &str) -> ValueKind {
+    eval_with(src, OverflowPolicy::Error).unwrap()
+}
+
+fn assert_overflow(result: Result<ValueKind, Error>, src: &str) {
+    match result {
+        Err(Error::Runtime(e)) => assert_eq!(e.kind.code(), "overflow", "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+#[test]
+fn whole_results_are_integers_again() {
+    assert!(matches!(eval("x = 1 / 3 * 3"), ValueKind::Integer(1)));
+    assert!(matches!(eval("x = 1 / 3 + 2 / 3"), ValueKind::Integer(1)));
+    assert!(matches!(eval("x = 1 / 6 + 1 / 3"), ValueKind::Rational(1, 2)));
+}
+
+#[test]
+fn the_denominator_is_positive() {
+    assert!(matches!(eval("x = -1 / -2"), ValueKind::Rational(1, 2)));
+    assert!(matches!(eval("x = 1 / -2"), ValueKind::Rational(-1, 2)));
+    assert!(matches!(eval("x = 1 / 2 / -3"), ValueKind::Rational(-1, 6)));
+}
+
+#[test]
+fn fractions_near_the_minimum_fit_until_they_dont() {
+    assert!(matches!(eval(&format!("{}x = m / 3", MIN)), ValueKind::Rational(i64::MIN, 3)));
+
+    // Making the denominator positive negates i64::MIN
+    for src in ["x = m / -3", "x = m / 3 - 1 / 2", "x = m / 3 * -3"] {
+        let src = format!("{}{}", MIN, src);
+        assert_overflow(eval_with(&src, OverflowPolicy::Error), &src);
+    }
+}
+
+#[test]
+fn fractions_overflow_under_every_policy() {
+    let src = format!("{}x = m / 3 - 1 / 2", MIN);
+
+    for policy in [OverflowPolicy::Error, OverflowPolicy::Wrap, OverflowPolicy::Saturate, OverflowPolicy::Promote] {
+        assert_overflow(eval_with(&src, policy), &src);
+    }
+}
+
+#[test]
+fn whole_results_that_dont_fit_are_promoted() {
+    let src = format!("{}x = m / 3 * -3", MIN);
+
+    match eval_with(&src, OverflowPolicy::Promote) {
+        Ok(ValueKind::BigInt(n)) => assert_eq!(n.to_string(), "9223372036854775808"),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+    assert_overflow(eval_with(&src, OverflowPolicy::Wrap), &src);
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use term::{Error, Interpreter, Value};
+
+fn assert_undefined(result: Result<Value, Error>, message: &str, col: usize) {
+    match result {
+        Err(Error::Runtime(e)) => {
+            assert_eq!(e.kind.code(), "undefined-variable");
+            assert_eq!(e.kind.to_string(), message);
+            assert_eq!(e.span.col, col);
+        },
+        other => panic!("expected an undefined variable, got {:?}", other)
+    }
+}
+
+#[test]
+fn nothing_runs_when_a_later_read_is_undefined() {
+    let mut interpreter = Interpreter::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    interpreter.register_fn("effect", move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(Value::Integer(1))
+    });
+
+    assert_undefined(interpreter.eval("a = effect()\nb = a + missing"), "No such variable: \"missing\"", 9);
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+    assert!(interpreter.get_var("a").is_none());
+}
+
+#[test]
+fn earlier_assignments_define_later_reads() {
+    let mut interpreter = Interpreter::new();
+
+    assert!(matches!(interpreter.eval("a = 1\nb = a + 1\nc = a + b"), Ok(Value::Integer(3))));
+    // A variable can't be read in its own first assignment
+    assert_undefined(interpreter.eval("total = total + 1"), "No such variable: \"total\"", 9);
+}
+
+#[test]
+fn host_variables_are_resolved() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_var("width", 4i64);
+
+    assert!(matches!(interpreter.eval("area = width * width"), Ok(Value::Integer(16))));
+    assert_undefined(interpreter.eval("x = widt"), "No such variable: \"widt\", did you mean \"width\"?", 5);
+}
+
+#[test]
+fn slots_line_up_after_restoring_a_snapshot() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("a = 1").unwrap();
+    let snapshot = interpreter.snapshot();
+
+    interpreter.eval("b = 2\nc = 3").unwrap();
+    interpreter.restore(snapshot);
+
+    assert!(interpreter.get_var("b").is_none());
+    assert_undefined(interpreter.eval("x = c"), "No such variable: \"c\", did you mean \"a\"?", 5);
+    assert!(matches!(interpreter.eval("c = a + 10\nx = c"), Ok(Value::Integer(11))));
+}
@@ -0,0 +1,27 @@
+use term::interpreter::EvalMode;
+use term::{interpret, State, ValueKind};
+
+fn integers(stack: &[ValueKind]) -> Vec<i64> {
+    stack.iter().map(|value| match value {
+        ValueKind::Integer(n) => *n,
+        other => panic!("expected an integer, got {:?}", other)
+    }).collect()
+}
+
+#[test]
+fn a_failure_keeps_the_operands_that_were_waiting() {
+    for mode in [EvalMode::Tree, EvalMode::Stack] {
+        let mut state = State::new();
+        state.eval_mode = mode;
+
+        interpret("a = 2", &mut state).unwrap();
+        assert!(state.stack_at_failure().is_none());
+
+        assert!(interpret("x = 1 + a * (3 - 1 / 0)", &mut state).is_err());
+        assert_eq!(integers(state.stack_at_failure().unwrap()), [1, 2, 3], "{:?}", mode);
+        assert!(state.stack_contents().is_empty());
+
+        interpret("y = 1", &mut state).unwrap();
+        assert!(state.stack_at_failure().is_none());
+    }
+}
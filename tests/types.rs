@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use term::interpreter::types::{infer, type_of, Type};
+use term::interpreter::parse;
+use term::ValueKind;
+
+fn infer_with(src: &str, variables: &[(&str, ValueKind)]) -> Result<Type, usize> {
+    let variables: HashMap<String, Type> = variables.iter().map(|(name, value)| (name.to_string(), type_of(value))).collect();
+    infer(&parse(src).unwrap(), variables).map_err(|errors| errors.len())
+}
+
+#[test]
+fn types_come_from_the_variables_that_exist() {
+    let vars = [("a", ValueKind::Integer(2)), ("s", ValueKind::from("text"))];
+
+    assert_eq!(infer_with("a * 3", &vars), Ok(Type::Integer));
+    assert_eq!(infer_with("a / 3", &vars), Ok(Type::Number));
+    assert_eq!(infer_with("s % a", &vars), Ok(Type::Str));
+    assert_eq!(infer_with("x = a; x < 1", &vars), Ok(Type::Boolean));
+    assert_eq!(infer_with("s + 1", &vars), Err(1));
+}
+
+#[test]
+fn calls_are_not_run() {
+    assert_eq!(infer_with("print(1)", &[]), Ok(Type::Unknown));
+    assert_eq!(infer_with("none", &[]), Ok(Type::None));
+}
+
+#[test]
+fn standard_builtins_have_known_types() {
+    assert_eq!(infer_with("str(1)", &[]), Ok(Type::Str));
+    assert_eq!(infer_with("len(\"abc\") + 1", &[]), Ok(Type::Integer));
+    assert_eq!(infer_with("to_bytes(\"a\")", &[]), Ok(Type::Bytes));
+    assert_eq!(infer_with("upper(\"a\") - 1", &[]), Err(1));
+}
@@ -0,0 +1,94 @@
+use term::{Interpreter, Value};
+
+fn eval(src: &str) -> Value {
+    Interpreter::new().eval(src).unwrap()
+}
+
+fn assert_str(src: &str, expected: &str) {
+    match eval(src) {
+        Value::Str(s) => assert_eq!(&*s, expected, "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+fn assert_int(src: &str, expected: i64) {
+    match eval(src) {
+        Value::Integer(n) => assert_eq!(n, expected, "{}", src),
+        other => panic!("{} evaluated to {:?}", src, other)
+    }
+}
+
+#[test]
+fn identifiers_can_be_non_ascii() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("größe = 3\nπ = größe * 2\n変数 = π + 1").unwrap();
+
+    assert!(matches!(interpreter.get_var("π"), Some(Value::Integer(6))));
+    assert!(matches!(interpreter.get_var("変数"), Some(Value::Integer(7))));
+}
+
+#[test]
+fn strings_keep_their_text() {
+    assert_str("\"naïve café\"", "naïve café");
+    assert_str("\"日本語\"", "日本語");
+    assert_str("\"🦀\"", "🦀");
+}
+
+#[test]
+fn length_counts_characters() {
+    assert_int("len(\"\")", 0);
+    assert_int("len(\"héllo\")", 5);
+    assert_int("len(\"日本語\")", 3);
+    assert_int("len(\"🦀🦀\")", 2);
+    assert_int("len(to_bytes(\"日本語\"))", 9);
+}
+
+#[test]
+fn indexing_and_slicing_count_characters() {
+    assert_str("char_at(\"日本語\", 1)", "本");
+    assert_str("char_at(\"🦀x\", 1)", "x");
+    assert_str("slice(\"naïve\", 2, 4)", "ïv");
+    assert_str("slice(\"日本語\", 0, 3)", "日本語");
+    assert_str("slice(\"日本語\", 3, 3)", "");
+}
+
+#[test]
+fn negative_offsets_count_from_the_end() {
+    assert_str("char_at(\"日本語\", -1)", "語");
+    assert_str("char_at(\"日本語\", -3)", "日");
+    assert_str("slice(\"naïve\", -3, -1)", "ïv");
+    assert_str("slice(\"naïve\", 0, -5)", "");
+    assert!(Interpreter::new().eval("char_at(\"日本語\", -4)").is_err());
+}
+
+#[test]
+fn indexing_past_the_end_fails() {
+    assert!(Interpreter::new().eval("char_at(\"日本語\", 3)").is_err());
+    assert!(Interpreter::new().eval("slice(\"日本語\", 2, 4)").is_err());
+    assert!(Interpreter::new().eval("slice(\"日本語\", 2, 1)").is_err());
+}
+
+#[test]
+fn case_mapping_is_unicode_aware() {
+    assert_str("upper(\"straße\")", "STRASSE");
+    assert_str("lower(\"ÉTÉ\")", "été");
+    assert_str("upper(\"ǆ\")", "Ǆ");
+    assert_str("lower(\"ΑΣ\")", "ας");
+}
+
+#[test]
+fn reversal_keeps_characters_whole() {
+    assert_str("reverse(\"añb\")", "bña");
+    assert_str("reverse(\"日本語\")", "語本日");
+    assert_str("reverse(\"a🦀\")", "🦀a");
+}
+
+#[test]
+fn slices_can_step_and_leave_bounds_open() {
+    assert_str("slice(\"日本語テキスト\", 0, none, 2)", "日語キト");
+    assert_str("slice(\"naïve\", none, none, -1)", "evïan");
+    assert_str("slice(\"naïve\", -1, 0, -2)", "eï");
+    assert_str("slice(\"naïve\", 2, none)", "ïve");
+    assert!(Interpreter::new().eval("slice(\"naïve\", 0, 5, 0)").is_err());
+    assert!(Interpreter::new().eval("slice(\"naïve\", 4, 1)").is_err());
+}